@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use crate::services::{backend, control, rt, settings::project_dirs};
+
+const SOCKET_FILE: &str = "taan.sock";
+
+/// Runs playback, the queue and Spotify Connect without a window, controlled over a Unix
+/// socket, until interrupted.
+pub fn run(args: &[String]) -> anyhow::Result<()> {
+    let socket = match args.iter().position(|a| a == "--socket") {
+        Some(i) => PathBuf::from(
+            args.get(i + 1)
+                .ok_or_else(|| anyhow::anyhow!("--socket needs a path"))?,
+        ),
+        None => default_socket()?,
+    };
+    let token = tokio_util::sync::CancellationToken::new();
+    let offline = args.iter().any(|a| a == "--offline");
+    let join = crate::setup(token.clone(), None, offline)?;
+    let result = rt().block_on(async {
+        login().await?;
+        tokio::select! {
+            result = control::serve(&socket) => result,
+            _ = shutdown_signal() => {
+                log::info!("Shutting down");
+                Ok(())
+            }
+        }
+    });
+    token.cancel();
+    join.join().unwrap();
+    result
+}
+
+/// The runtime directory when the platform has one, the cache directory otherwise.
+fn default_socket() -> anyhow::Result<PathBuf> {
+    let dirs = project_dirs()?;
+    let dir = dirs.runtime_dir().unwrap_or(dirs.cache_dir());
+    Ok(dir.join(SOCKET_FILE))
+}
+
+/// Logs in with the cached credentials, falling back to the OAuth flow, which prints the
+/// URL to open when there is no browser.
+async fn login() -> anyhow::Result<()> {
+    if let Err(e) = backend().init().await {
+        log::warn!("No cached login, starting OAuth: {:#}", e);
+        backend().auth().await?;
+    }
+    log::info!("Successfuly logged in");
+    Ok(())
+}
+
+async fn shutdown_signal() {
+    let mut terminate =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()).ok();
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        Some(_) = async { terminate.as_mut()?.recv().await } => {}
+    }
+}
@@ -1,10 +1,19 @@
 slint::include_modules!();
 
+#[cfg(unix)]
+mod headless;
 mod models;
 mod services;
 mod viewmodels;
 
 pub fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--headless") {
+        #[cfg(unix)]
+        return headless::run(&args);
+        #[cfg(not(unix))]
+        anyhow::bail!("Headless mode needs Unix domain sockets");
+    }
     #[cfg(target_os = "windows")]
     {
         use i_slint_backend_winit::winit::platform::windows::{
@@ -20,7 +29,8 @@ pub fn main() -> anyhow::Result<()> {
     }
     let token = tokio_util::sync::CancellationToken::new();
     let ui = MainWindow::new()?;
-    let join = setup(token.clone(), ui.as_weak())?;
+    let offline = args.iter().any(|a| a == "--offline");
+    let join = setup(token.clone(), Some(ui.as_weak()), offline)?;
 
     viewmodels::init()?;
 
@@ -30,21 +40,40 @@ pub fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Starts the runtime and the services, `ui_weak` is unset when running headless. `offline`
+/// swaps Spotify for the in-memory backend with sample tracks.
 fn setup(
     token: tokio_util::sync::CancellationToken,
-    ui_weak: slint::Weak<MainWindow>,
-) -> tokio::io::Result<std::thread::JoinHandle<()>> {
+    ui_weak: Option<slint::Weak<MainWindow>>,
+    offline: bool,
+) -> anyhow::Result<std::thread::JoinHandle<()>> {
     env_logger::init();
+    let settings = services::settings::Settings::load().unwrap_or_else(|e| {
+        log::error!("Invalid settings, falling back to defaults: {:#}", e);
+        services::settings::Settings::fallback(&e)
+    });
     let rt = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
     let rt_handle = rt.handle().clone();
+    let shutdown = token.clone();
     let join = std::thread::spawn(move || {
         rt.block_on(token.cancelled());
         log::info!("Tokio Thread closed");
     });
-    let spot = rt_handle.block_on(async { services::spotify::SpotifyService::default() });
-    services::init(spot, rt_handle, ui_weak);
+    if offline {
+        log::info!("Running offline on sample tracks");
+        services::init(
+            services::fake::FakeBackend::sample(),
+            settings,
+            rt_handle,
+            ui_weak,
+        );
+        return Ok(join);
+    }
+    let spot = rt_handle
+        .block_on(async { services::spotify::SpotifyService::new(&settings, shutdown) })?;
+    services::init(spot, settings, rt_handle, ui_weak);
     Ok(join)
 }
 
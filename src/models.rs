@@ -1,3 +1,24 @@
+pub mod album;
+pub mod artist;
 pub mod authentication;
+pub mod devices;
+pub mod lyrics;
 pub mod player;
+pub mod playlists;
+pub mod search;
+pub mod shows;
+pub mod toast;
 pub mod tracks;
+
+use rspotify::model::Image;
+
+// Spotify lists the largest image first.
+/// The smallest image of a cover, for list rows and cards.
+pub fn thumbnail(images: &[Image]) -> Option<&str> {
+    images.last().map(|i| i.url.as_str())
+}
+
+/// The largest image of a cover, for page headers and the player.
+pub fn large_image(images: &[Image]) -> Option<&str> {
+    images.first().map(|i| i.url.as_str())
+}
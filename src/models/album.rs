@@ -0,0 +1,121 @@
+use rspotify::model::{FullAlbum, SimplifiedTrack};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::services::ui_weak;
+
+/// Shows an empty page for album `id` until its details arrive.
+pub fn open(id: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        album.set_id(id.into());
+        album.set_name("".into());
+        album.set_artists(slint::ModelRc::default());
+        album.set_cover_art(slint::Image::default());
+        album.set_release_date("".into());
+        album.set_label("".into());
+        album.set_total_duration(0);
+        album.set_disc_count(1);
+        album.set_saved(false);
+        album.set_error("".into());
+        album.set_tracks(slint::ModelRc::new(
+            slint::VecModel::<crate::AlbumTrack>::default(),
+        ));
+        album.set_open(true);
+    })?;
+    Ok(())
+}
+
+pub fn set_details(details: FullAlbum) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        if album.get_id() != details.id.id() {
+            return;
+        }
+        album.set_name(details.name.into());
+        album.set_artists(super::tracks::artist_links(details.artists));
+        album.set_release_date(details.release_date.into());
+        album.set_label(details.label.unwrap_or_default().into());
+    })?;
+    Ok(())
+}
+
+pub fn add_tracks(id: String, l: Vec<SimplifiedTrack>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        if album.get_id() != id {
+            return;
+        }
+        let tracks = album.get_tracks();
+        let list = tracks
+            .as_any()
+            .downcast_ref::<slint::VecModel<crate::AlbumTrack>>()
+            .unwrap();
+        let mut total_duration = album.get_total_duration();
+        let mut disc_count = album.get_disc_count();
+        for track in l {
+            let Some(track_id) = track.id else {
+                continue;
+            };
+            let duration = track.duration.num_milliseconds() as i32;
+            total_duration += duration;
+            disc_count = disc_count.max(track.disc_number);
+            list.push(crate::AlbumTrack {
+                id: track_id.id().into(),
+                title: track.name.into(),
+                artist: track
+                    .artists
+                    .into_iter()
+                    .map(|a| a.name)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+                    .into(),
+                duration,
+                disc_number: track.disc_number,
+                track_number: track.track_number as i32,
+            });
+        }
+        album.set_total_duration(total_duration);
+        album.set_disc_count(disc_count);
+    })?;
+    Ok(())
+}
+
+pub fn set_cover_art(
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        if album.get_id() == id {
+            album.set_cover_art(slint::Image::from_rgba8(img));
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_saved(id: String, saved: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        if album.get_id() == id {
+            album.set_saved(saved);
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_fetching(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        album.set_fetching(x);
+    })?;
+    Ok(())
+}
+
+pub fn set_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let album = ui.global::<crate::AlbumState>();
+        album.set_error(error.into());
+    })?;
+    Ok(())
+}
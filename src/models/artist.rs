@@ -0,0 +1,178 @@
+use rspotify::model::{FullArtist, FullTrack, SimplifiedAlbum};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::services::ui_weak;
+
+/// Shows an empty page for artist `id` until its details arrive.
+pub fn open(id: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        artist.set_id(id.into());
+        artist.set_name("".into());
+        artist.set_image(slint::Image::default());
+        artist.set_followers(0);
+        artist.set_genres("".into());
+        artist.set_following(false);
+        artist.set_error("".into());
+        artist.set_top_tracks(slint::ModelRc::default());
+        artist.set_albums(empty_cards());
+        artist.set_singles(empty_cards());
+        artist.set_compilations(empty_cards());
+        artist.set_related(slint::ModelRc::default());
+        artist.set_open(true);
+        // The artist page sits below album pages, an open album would hide it.
+        ui.global::<crate::AlbumState>().set_open(false);
+    })?;
+    Ok(())
+}
+
+fn empty_cards() -> slint::ModelRc<crate::Card> {
+    slint::ModelRc::new(slint::VecModel::<crate::Card>::default())
+}
+
+pub fn set_details(details: FullArtist) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() != details.id.id() {
+            return;
+        }
+        artist.set_name(details.name.into());
+        artist.set_followers(details.followers.total as i32);
+        artist.set_genres(details.genres.join(", ").into());
+    })?;
+    Ok(())
+}
+
+pub fn set_top_tracks(id: String, l: Vec<FullTrack>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() != id {
+            return;
+        }
+        let tracks: Vec<crate::Track> = l.into_iter().filter_map(super::tracks::to_track).collect();
+        artist.set_top_tracks(slint::ModelRc::new(slint::VecModel::from(tracks)));
+    })?;
+    Ok(())
+}
+
+/// Sorts releases into albums, singles and compilations.
+pub fn add_albums(id: String, l: Vec<SimplifiedAlbum>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() != id {
+            return;
+        }
+        for album in l {
+            let Some(album_id) = album.id else {
+                continue;
+            };
+            let group = album.album_group.or(album.album_type).unwrap_or_default();
+            let cards = match group.as_str() {
+                "single" => artist.get_singles(),
+                "compilation" => artist.get_compilations(),
+                _ => artist.get_albums(),
+            };
+            let release_year = album
+                .release_date
+                .as_deref()
+                .and_then(|date| date.split('-').next())
+                .unwrap_or_default()
+                .to_string();
+            cards
+                .as_any()
+                .downcast_ref::<slint::VecModel<crate::Card>>()
+                .unwrap()
+                .push(crate::Card {
+                    id: album_id.id().into(),
+                    title: album.name.into(),
+                    subtitle: release_year.into(),
+                    cover_art: slint::Image::default(),
+                });
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_related(id: String, l: Vec<FullArtist>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() != id {
+            return;
+        }
+        let related: Vec<crate::Card> = l
+            .into_iter()
+            .map(|related| crate::Card {
+                id: related.id.id().into(),
+                title: related.name.into(),
+                subtitle: "Artist".into(),
+                cover_art: slint::Image::default(),
+            })
+            .collect();
+        artist.set_related(slint::ModelRc::new(slint::VecModel::from(related)));
+    })?;
+    Ok(())
+}
+
+pub fn set_image(
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() == id {
+            artist.set_image(slint::Image::from_rgba8(img));
+        }
+    })?;
+    Ok(())
+}
+
+/// Sets the picture of the album or related artist `card_id` shown on the page.
+pub fn set_card_art(
+    card_id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        for cards in [
+            artist.get_albums(),
+            artist.get_singles(),
+            artist.get_compilations(),
+            artist.get_related(),
+        ] {
+            if let Some(index) = cards.iter().position(|c| c.id == card_id) {
+                let mut card = cards.row_data(index).unwrap();
+                card.cover_art = slint::Image::from_rgba8(img);
+                cards.set_row_data(index, card);
+                return;
+            }
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_following(id: String, following: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        if artist.get_id() == id {
+            artist.set_following(following);
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_fetching(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        artist.set_fetching(x);
+    })?;
+    Ok(())
+}
+
+pub fn set_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let artist = ui.global::<crate::ArtistState>();
+        artist.set_error(error.into());
+    })?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::services::ui_weak;
+use crate::services::{error::Error, spotify::SessionHealth, ui_weak};
 use slint::ComponentHandle;
 
 /**
@@ -10,19 +10,36 @@ pub fn login_succeeded() -> anyhow::Result<()> {
         auth_state.set_loggedIn(true);
         auth_state.set_loading(false);
         auth_state.set_login_in_progress(false);
+        auth_state.set_error("".into());
     })?;
     Ok(())
 }
 /**
  * Can be called from any thread
  */
-pub fn login_failed(_error: &str) -> anyhow::Result<()> {
-    ui_weak().upgrade_in_event_loop(|ui| {
+pub fn login_failed(error: Option<&Error>) -> anyhow::Result<()> {
+    let message = error.map(Error::to_string).unwrap_or_default();
+    ui_weak().upgrade_in_event_loop(move |ui| {
         let auth_state = ui.global::<crate::AuthenticationState>();
         auth_state.set_loggedIn(false);
         auth_state.set_loading(false);
         auth_state.set_login_in_progress(false);
-        // TODO: Add error handling in UI
+        auth_state.set_error(message.into());
+    })?;
+    Ok(())
+}
+/**
+ * Can be called from any thread
+ */
+pub fn set_session_health(health: SessionHealth) -> anyhow::Result<()> {
+    let session = match health {
+        SessionHealth::Connected => "connected",
+        SessionHealth::Reconnecting => "reconnecting",
+        SessionHealth::Disconnected => "disconnected",
+    };
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let auth_state = ui.global::<crate::AuthenticationState>();
+        auth_state.set_session(session.into());
     })?;
     Ok(())
 }
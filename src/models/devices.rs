@@ -0,0 +1,27 @@
+use slint::{ComponentHandle, ModelRc, VecModel};
+
+use crate::services::ui_weak;
+
+pub fn set_devices(devices: Vec<crate::Device>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::DevicesState>();
+        app.set_devices(ModelRc::new(VecModel::from(devices)));
+    })?;
+    Ok(())
+}
+
+pub fn set_fetching(fetching: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::DevicesState>();
+        app.set_fetching(fetching);
+    })?;
+    Ok(())
+}
+
+pub fn set_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::DevicesState>();
+        app.set_error(error.into());
+    })?;
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use slint::{ComponentHandle, Model};
+
+use crate::services::lyrics::{self, Lyrics};
+use crate::services::ui_weak;
+
+/// Clears the panel while the lyrics of track `id` load.
+pub fn open_track(id: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let state = ui.global::<crate::LyricsState>();
+        state.set_track_id(id.into());
+        state.set_lines(slint::ModelRc::default());
+        state.set_synced(false);
+        state.set_current_line(-1);
+        state.set_source("".into());
+        state.set_error("".into());
+        state.set_fetching(true);
+    })?;
+    Ok(())
+}
+
+pub fn set_lyrics(id: String, lyrics: Option<Lyrics>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let state = ui.global::<crate::LyricsState>();
+        if state.get_track_id() != id {
+            return;
+        }
+        state.set_fetching(false);
+        let Some(lyrics) = lyrics else {
+            state.set_error("No lyrics for this track".into());
+            return;
+        };
+        let lines: Vec<crate::LyricLine> = lyrics
+            .lines
+            .into_iter()
+            .map(|line| crate::LyricLine {
+                start: line.start_ms as i32,
+                words: line.words.into(),
+            })
+            .collect();
+        state.set_lines(slint::ModelRc::new(slint::VecModel::from(lines)));
+        state.set_synced(lyrics.synced);
+        state.set_source(lyrics.source.into());
+        let position = ui.global::<crate::PlayerState>().get_current_time();
+        follow_position(&ui, position);
+    })?;
+    Ok(())
+}
+
+/// Highlights the line sung at `position_ms`, called from within the event loop.
+pub fn follow_position(ui: &crate::MainWindow, position_ms: i32) {
+    let state = ui.global::<crate::LyricsState>();
+    if !state.get_synced() {
+        return;
+    }
+    let lines = state.get_lines();
+    let starts = lines.iter().map(|l| l.start.max(0) as u32);
+    let current = lyrics::current_line(starts, position_ms.max(0) as u32);
+    state.set_current_line(current.map_or(-1, |i| i as i32));
+}
@@ -13,22 +13,60 @@ pub fn set_track_details(
             librespot_metadata::audio::UniqueFields::Track { artists, album, .. } => {
                 use librespot_protocol::metadata::artist_with_role::ArtistRole;
                 let mut composers = vec![];
+                let mut main_artists = vec![];
                 for artist in artists.0 {
                     if artist.role == ArtistRole::ARTIST_ROLE_MAIN_ARTIST {
-                        app.set_artist_name(artist.name.into());
+                        main_artists.push(crate::ArtistLink {
+                            id: artist.id.to_base62().unwrap_or_default().into(),
+                            name: artist.name.into(),
+                        });
                     } else if artist.role == ArtistRole::ARTIST_ROLE_COMPOSER {
                         composers.push(artist.name);
                     }
                 }
+                let artist_names = main_artists
+                    .iter()
+                    .map(|a| a.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                app.set_artist_name(artist_names.into());
+                app.set_artists(slint::ModelRc::new(slint::VecModel::from(main_artists)));
                 let composer_str = composers.join(", ");
                 app.set_composer(composer_str.into());
                 app.set_album(album.into());
+                app.set_is_episode(false);
+                app.set_show_name("".into());
+                app.set_publisher("".into());
+                app.set_description("".into());
+                app.set_release_date("".into());
+            }
+            librespot_metadata::audio::UniqueFields::Episode {
+                description,
+                publish_time,
+                show_name,
+            } => {
+                app.set_artist_name(show_name.clone().into());
+                app.set_artists(slint::ModelRc::default());
+                app.set_composer("".into());
+                app.set_album("".into());
+                app.set_is_episode(true);
+                app.set_show_name(show_name.into());
+                // The publisher is not part of the audio item, it arrives through set_publisher.
+                app.set_publisher("".into());
+                app.set_description(description.into());
+                app.set_release_date(publish_time.date().to_string().into());
             }
-            librespot_metadata::audio::UniqueFields::Episode { .. } => todo!(),
         }
     })?;
     Ok(())
 }
+pub fn set_publisher(publisher: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_publisher(publisher.into());
+    })?;
+    Ok(())
+}
 pub fn set_cover_art(img: slint::SharedPixelBuffer<slint::Rgba8Pixel>) -> anyhow::Result<()> {
     ui_weak().upgrade_in_event_loop(move |ui| {
         let app = ui.global::<crate::PlayerState>();
@@ -54,6 +92,117 @@ pub fn set_position(position_ms: u32) -> anyhow::Result<()> {
     ui_weak().upgrade_in_event_loop(move |ui| {
         let app = ui.global::<crate::PlayerState>();
         app.set_current_time((position_ms) as i32);
+        super::lyrics::follow_position(&ui, position_ms as i32);
+    })?;
+    Ok(())
+}
+pub fn set_shuffle(shuffle: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_shuffle(shuffle);
+    })?;
+    Ok(())
+}
+pub fn set_repeat(context: bool, track: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_repeat(if track {
+            crate::RepeatMode::Track
+        } else if context {
+            crate::RepeatMode::Context
+        } else {
+            crate::RepeatMode::Off
+        });
+    })?;
+    Ok(())
+}
+pub fn set_volume(volume: u16) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_volume(volume as f32 / u16::MAX as f32);
+    })?;
+    Ok(())
+}
+pub fn set_streaming_quality(quality: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_streaming_quality(quality.into());
+    })?;
+    Ok(())
+}
+pub fn set_output_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_output_error(error.into());
+    })?;
+    Ok(())
+}
+pub fn set_device_name(name: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_device_name(name.into());
+    })?;
+    Ok(())
+}
+/// Shows the playback state of a remote Connect device.
+pub fn set_remote_playback(
+    playback: rspotify::model::CurrentPlaybackContext,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let app = ui.global::<crate::PlayerState>();
+        app.set_device_name(playback.device.name.into());
+        app.set_is_playing(playback.is_playing);
+        app.set_shuffle(playback.shuffle_state);
+        app.set_repeat(match playback.repeat_state {
+            rspotify::model::RepeatState::Off => crate::RepeatMode::Off,
+            rspotify::model::RepeatState::Context => crate::RepeatMode::Context,
+            rspotify::model::RepeatState::Track => crate::RepeatMode::Track,
+        });
+        if let Some(volume) = playback.device.volume_percent {
+            app.set_volume(volume as f32 / 100.0);
+        }
+        if let Some(progress) = playback.progress {
+            app.set_current_time(progress.num_milliseconds() as i32);
+            super::lyrics::follow_position(&ui, progress.num_milliseconds() as i32);
+        }
+        app.set_streaming_quality("".into());
+        match playback.item {
+            Some(rspotify::model::PlayableItem::Track(track)) => {
+                app.set_song_title(track.name.into());
+                app.set_music_duration(track.duration.num_milliseconds() as i32);
+                app.set_artist_name(
+                    track
+                        .artists
+                        .iter()
+                        .map(|a| a.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                        .into(),
+                );
+                app.set_artists(super::tracks::artist_links(track.artists));
+                app.set_composer("".into());
+                app.set_album(track.album.name.into());
+                app.set_is_episode(false);
+                app.set_show_name("".into());
+                app.set_publisher("".into());
+                app.set_description("".into());
+                app.set_release_date("".into());
+            }
+            Some(rspotify::model::PlayableItem::Episode(episode)) => {
+                app.set_song_title(episode.name.into());
+                app.set_music_duration(episode.duration.num_milliseconds() as i32);
+                app.set_artist_name(episode.show.name.clone().into());
+                app.set_artists(slint::ModelRc::default());
+                app.set_composer("".into());
+                app.set_album("".into());
+                app.set_is_episode(true);
+                app.set_show_name(episode.show.name.into());
+                app.set_publisher(episode.show.publisher.into());
+                app.set_description(episode.description.into());
+                app.set_release_date(episode.release_date.into());
+            }
+            _ => {}
+        }
     })?;
     Ok(())
 }
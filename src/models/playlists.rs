@@ -0,0 +1,62 @@
+use rspotify::model::SimplifiedPlaylist;
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::services::ui_weak;
+
+pub fn set_fetching_playlists(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let playlists = ui.global::<crate::PlaylistsState>();
+        playlists.set_fetching_playlists(x);
+    })?;
+    Ok(())
+}
+
+pub fn clear_playlists() -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let playlists = ui.global::<crate::PlaylistsState>();
+        playlists.set_playlists(slint::ModelRc::new(
+            slint::VecModel::<crate::Playlist>::default(),
+        ));
+    })?;
+    Ok(())
+}
+
+pub fn add_playlists(l: Vec<SimplifiedPlaylist>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let playlists = ui.global::<crate::PlaylistsState>().get_playlists();
+        let list = playlists
+            .as_any()
+            .downcast_ref::<slint::VecModel<crate::Playlist>>()
+            .unwrap();
+        for playlist in l {
+            list.push(crate::Playlist {
+                id: playlist.id.id().into(),
+                name: playlist.name.into(),
+                owner: playlist
+                    .owner
+                    .display_name
+                    .unwrap_or(playlist.owner.id.id().to_string())
+                    .into(),
+                track_count: playlist.tracks.total as i32,
+                cover_art: slint::Image::default(),
+            });
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_cover_art(
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let playlists = ui.global::<crate::PlaylistsState>().get_playlists();
+        if let Some(index) = playlists.iter().position(|p| p.id == id) {
+            let mut playlist = playlists.row_data(index).unwrap();
+            playlist.cover_art = slint::Image::from_rgba8(img);
+            playlists.set_row_data(index, playlist);
+        }
+    })?;
+    Ok(())
+}
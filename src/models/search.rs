@@ -0,0 +1,264 @@
+use rspotify::model::{Page, SearchMultipleResult, SearchType};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use super::thumbnail;
+use crate::services::ui_weak;
+
+/// A search result prepared off the UI thread.
+pub struct Item {
+    pub id: String,
+    pub title: String,
+    pub subtitle: String,
+    pub duration: i32,
+    /// Thumbnail to fetch once the item is shown.
+    pub image: Option<String>,
+}
+
+/// The results of one search type.
+pub struct Section {
+    pub kind: SearchType,
+    pub items: Vec<Item>,
+    pub has_more: bool,
+    /// Offset of the next page on the server, which can be past the items kept here.
+    pub next_offset: u32,
+}
+
+/// Splits a search response into one section per requested type, in the order results are
+/// shown.
+pub fn sections(result: SearchMultipleResult) -> Vec<Section> {
+    let mut sections = vec![];
+    if let Some(page) = result.tracks {
+        sections.push(Section {
+            kind: SearchType::Track,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .filter_map(|track| {
+                    Some(Item {
+                        id: track.id?.id().to_string(),
+                        subtitle: join_artists(track.artists.into_iter().map(|a| a.name)),
+                        duration: track.duration.num_milliseconds() as i32,
+                        image: thumbnail(&track.album.images).map(str::to_string),
+                        title: track.name,
+                    })
+                })
+                .collect(),
+        });
+    }
+    if let Some(page) = result.artists {
+        sections.push(Section {
+            kind: SearchType::Artist,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .map(|artist| Item {
+                    id: artist.id.id().to_string(),
+                    title: artist.name,
+                    subtitle: "Artist".into(),
+                    duration: 0,
+                    image: thumbnail(&artist.images).map(str::to_string),
+                })
+                .collect(),
+        });
+    }
+    if let Some(page) = result.albums {
+        sections.push(Section {
+            kind: SearchType::Album,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .filter_map(|album| {
+                    Some(Item {
+                        id: album.id?.id().to_string(),
+                        subtitle: join_artists(album.artists.into_iter().map(|a| a.name)),
+                        duration: 0,
+                        image: thumbnail(&album.images).map(str::to_string),
+                        title: album.name,
+                    })
+                })
+                .collect(),
+        });
+    }
+    if let Some(page) = result.playlists {
+        sections.push(Section {
+            kind: SearchType::Playlist,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .map(|playlist| Item {
+                    id: playlist.id.id().to_string(),
+                    subtitle: playlist
+                        .owner
+                        .display_name
+                        .unwrap_or(playlist.owner.id.id().to_string()),
+                    duration: 0,
+                    image: thumbnail(&playlist.images).map(str::to_string),
+                    title: playlist.name,
+                })
+                .collect(),
+        });
+    }
+    if let Some(page) = result.shows {
+        sections.push(Section {
+            kind: SearchType::Show,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .map(|show| Item {
+                    id: show.id.id().to_string(),
+                    title: show.name,
+                    subtitle: show.publisher,
+                    duration: 0,
+                    image: thumbnail(&show.images).map(str::to_string),
+                })
+                .collect(),
+        });
+    }
+    if let Some(page) = result.episodes {
+        sections.push(Section {
+            kind: SearchType::Episode,
+            has_more: page.next.is_some(),
+            next_offset: next_offset(&page),
+            items: page
+                .items
+                .into_iter()
+                .map(|episode| Item {
+                    id: episode.id.id().to_string(),
+                    title: episode.name,
+                    subtitle: episode.release_date,
+                    duration: episode.duration.num_milliseconds() as i32,
+                    image: thumbnail(&episode.images).map(str::to_string),
+                })
+                .collect(),
+        });
+    }
+    sections
+}
+
+/// Items the server dropped as null still count towards the offset, so step by the page's
+/// limit rather than by what arrived.
+fn next_offset<T: serde::de::DeserializeOwned>(page: &Page<T>) -> u32 {
+    page.offset + page.limit.max(page.items.len() as u32)
+}
+
+fn join_artists(names: impl Iterator<Item = String>) -> String {
+    names.collect::<Vec<String>>().join(", ")
+}
+
+fn title(kind: SearchType) -> &'static str {
+    match kind {
+        SearchType::Track => "Songs",
+        SearchType::Artist => "Artists",
+        SearchType::Album => "Albums",
+        SearchType::Playlist => "Playlists",
+        SearchType::Show => "Podcasts",
+        SearchType::Episode => "Episodes",
+    }
+}
+
+fn to_search_item(kind: SearchType, item: Item) -> crate::SearchItem {
+    crate::SearchItem {
+        id: item.id.into(),
+        kind: Into::<&str>::into(kind).into(),
+        title: item.title.into(),
+        subtitle: item.subtitle.into(),
+        duration: item.duration,
+        cover_art: slint::Image::default(),
+    }
+}
+
+pub fn set_searching(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let search = ui.global::<crate::SearchState>();
+        search.set_searching(x);
+    })?;
+    Ok(())
+}
+
+pub fn set_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let search = ui.global::<crate::SearchState>();
+        search.set_error(error.into());
+    })?;
+    Ok(())
+}
+
+/// Replaces all shown results, dropping sections without any items.
+pub fn set_sections(l: Vec<Section>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let sections = slint::VecModel::<crate::SearchSection>::default();
+        for section in l.into_iter().filter(|s| !s.items.is_empty()) {
+            let kind = section.kind;
+            let items = section
+                .items
+                .into_iter()
+                .map(|item| to_search_item(kind, item))
+                .collect::<Vec<_>>();
+            sections.push(crate::SearchSection {
+                kind: Into::<&str>::into(kind).into(),
+                title: title(kind).into(),
+                items: slint::ModelRc::new(slint::VecModel::from(items)),
+                has_more: section.has_more,
+                next_offset: section.next_offset as i32,
+            });
+        }
+        let search = ui.global::<crate::SearchState>();
+        search.set_sections(slint::ModelRc::new(sections));
+    })?;
+    Ok(())
+}
+
+/// Appends the next page of results to the section of the same kind.
+pub fn extend_section(section: Section) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let sections = ui.global::<crate::SearchState>().get_sections();
+        let kind: &str = section.kind.into();
+        let Some(index) = sections.iter().position(|s| s.kind == kind) else {
+            return;
+        };
+        let mut row = sections.row_data(index).unwrap();
+        let items = row
+            .items
+            .as_any()
+            .downcast_ref::<slint::VecModel<crate::SearchItem>>()
+            .unwrap();
+        for item in section.items {
+            items.push(to_search_item(section.kind, item));
+        }
+        row.has_more = section.has_more;
+        row.next_offset = section.next_offset as i32;
+        sections.set_row_data(index, row);
+    })?;
+    Ok(())
+}
+
+pub fn set_cover_art(
+    kind: SearchType,
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let sections = ui.global::<crate::SearchState>().get_sections();
+        let kind: &str = kind.into();
+        let Some(section) = sections.iter().find(|s| s.kind == kind) else {
+            return;
+        };
+        if let Some(index) = section.items.iter().position(|i| i.id == id) {
+            let mut item = section.items.row_data(index).unwrap();
+            item.cover_art = slint::Image::from_rgba8(img);
+            section.items.set_row_data(index, item);
+        }
+    })?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+use rspotify::model::{FullShow, Show, SimplifiedEpisode};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::services::ui_weak;
+
+pub fn set_fetching_shows(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let shows = ui.global::<crate::ShowsState>();
+        shows.set_fetching(x);
+    })?;
+    Ok(())
+}
+
+pub fn clear_shows() -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let shows = ui.global::<crate::ShowsState>();
+        shows.set_shows(slint::ModelRc::new(
+            slint::VecModel::<crate::Card>::default(),
+        ));
+    })?;
+    Ok(())
+}
+
+pub fn add_shows(l: Vec<Show>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let shows = ui.global::<crate::ShowsState>().get_shows();
+        let list = shows
+            .as_any()
+            .downcast_ref::<slint::VecModel<crate::Card>>()
+            .unwrap();
+        for saved in l {
+            list.push(crate::Card {
+                id: saved.show.id.id().into(),
+                title: saved.show.name.into(),
+                subtitle: saved.show.publisher.into(),
+                cover_art: slint::Image::default(),
+            });
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_show_cover_art(
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let shows = ui.global::<crate::ShowsState>().get_shows();
+        if let Some(index) = shows.iter().position(|s| s.id == id) {
+            let mut show = shows.row_data(index).unwrap();
+            show.cover_art = slint::Image::from_rgba8(img);
+            shows.set_row_data(index, show);
+        }
+    })?;
+    Ok(())
+}
+
+/// Shows an empty page for show `id` until its details arrive.
+pub fn open(id: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        show.set_id(id.into());
+        show.set_name("".into());
+        show.set_publisher("".into());
+        show.set_description("".into());
+        show.set_cover_art(slint::Image::default());
+        show.set_error("".into());
+        show.set_episodes(slint::ModelRc::new(
+            slint::VecModel::<crate::Episode>::default(),
+        ));
+        show.set_open(true);
+        // Album and artist pages sit above show pages and would hide it.
+        ui.global::<crate::AlbumState>().set_open(false);
+        ui.global::<crate::ArtistState>().set_open(false);
+    })?;
+    Ok(())
+}
+
+pub fn set_details(details: FullShow) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        if show.get_id() != details.id.id() {
+            return;
+        }
+        show.set_name(details.name.into());
+        show.set_publisher(details.publisher.into());
+        show.set_description(details.description.into());
+    })?;
+    Ok(())
+}
+
+pub fn add_episodes(id: String, l: Vec<SimplifiedEpisode>) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        if show.get_id() != id {
+            return;
+        }
+        let episodes = show.get_episodes();
+        let list = episodes
+            .as_any()
+            .downcast_ref::<slint::VecModel<crate::Episode>>()
+            .unwrap();
+        for episode in l {
+            let (resume_position, fully_played) = episode
+                .resume_point
+                .map(|p| (p.resume_position.num_milliseconds() as i32, p.fully_played))
+                .unwrap_or_default();
+            list.push(crate::Episode {
+                id: episode.id.id().into(),
+                title: episode.name.into(),
+                description: episode.description.into(),
+                release_date: episode.release_date.into(),
+                duration: episode.duration.num_milliseconds() as i32,
+                resume_position,
+                fully_played,
+            });
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_cover_art(
+    id: String,
+    img: slint::SharedPixelBuffer<slint::Rgba8Pixel>,
+) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        if show.get_id() == id {
+            show.set_cover_art(slint::Image::from_rgba8(img));
+        }
+    })?;
+    Ok(())
+}
+
+pub fn set_fetching(x: bool) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        show.set_fetching(x);
+    })?;
+    Ok(())
+}
+
+pub fn set_error(error: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let show = ui.global::<crate::ShowState>();
+        show.set_error(error.into());
+    })?;
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use crate::services::{error::Error, ui_weak};
+use slint::ComponentHandle;
+
+/**
+ * Can be called from any thread
+ */
+pub fn show_error(error: &Error) -> anyhow::Result<()> {
+    let kind = error.kind();
+    let message = error.to_string();
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let toast = ui.global::<crate::ToastState>();
+        toast.set_kind(kind.into());
+        toast.set_message(message.into());
+        toast.set_visible(true);
+    })?;
+    Ok(())
+}
@@ -1,5 +1,5 @@
 use crate::services::ui_weak;
-use rspotify::model::{FullTrack, PlayableItem, PlaylistItem};
+use rspotify::model::{FullTrack, SimplifiedArtist};
 use rspotify::prelude::*;
 use slint::{ComponentHandle, Model};
 
@@ -11,6 +11,32 @@ pub fn set_current_track(uri: String) -> anyhow::Result<()> {
     Ok(())
 }
 
+pub fn set_context_uri(uri: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let tracks = ui.global::<crate::TracksState>();
+        tracks.set_context_uri(uri.into());
+    })?;
+    Ok(())
+}
+
+pub fn set_title(title: String) -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let tracks = ui.global::<crate::TracksState>();
+        tracks.set_title(title.into());
+    })?;
+    Ok(())
+}
+
+pub fn clear_tracks() -> anyhow::Result<()> {
+    ui_weak().upgrade_in_event_loop(move |ui| {
+        let tracks = ui.global::<crate::TracksState>();
+        tracks.set_tracks(slint::ModelRc::new(
+            slint::VecModel::<crate::Track>::default(),
+        ));
+    })?;
+    Ok(())
+}
+
 pub fn set_fetching_tracks(x: bool) -> anyhow::Result<()> {
     ui_weak().upgrade_in_event_loop(move |ui| {
         let tracks = ui.global::<crate::TracksState>();
@@ -26,24 +52,45 @@ pub fn add_tracks(l: Vec<FullTrack>) -> anyhow::Result<()> {
             .as_any()
             .downcast_ref::<slint::VecModel<crate::Track>>()
             .unwrap();
-        for track in l {
-            // Create SharedPixelBuffer and load the image from the URL
-            let t = crate::Track {
-                id: track.id.unwrap().id().into(),
-                title: track.name.into(),
-                duration: track.duration.num_milliseconds() as i32,
-                album: track.album.name.into(),
-                artist: track
-                    .artists
-                    .into_iter()
-                    .map(|a| a.name)
-                    .collect::<Vec<String>>()
-                    .join(", ")
-                    .into(),
-                cover_art: slint::Image::default(),
-            };
-            list.push(t);
+        for track in l.into_iter().filter_map(to_track) {
+            list.push(track);
         }
     })?;
     Ok(())
 }
+
+/// Converts a Web API track for the UI, skipping local files which have no Spotify id and cannot
+/// be streamed.
+pub fn to_track(track: FullTrack) -> Option<crate::Track> {
+    let id = track.id?;
+    Some(crate::Track {
+        id: id.id().into(),
+        title: track.name.into(),
+        duration: track.duration.num_milliseconds() as i32,
+        album: track.album.name.into(),
+        album_id: track
+            .album
+            .id
+            .map(|id| id.id().to_string())
+            .unwrap_or_default()
+            .into(),
+        artists: artist_links(track.artists),
+        cover_art: slint::Image::default(),
+    })
+}
+
+/// Keeps the ids of `artists` so their names can open the artist page.
+pub fn artist_links(artists: Vec<SimplifiedArtist>) -> slint::ModelRc<crate::ArtistLink> {
+    let links: Vec<crate::ArtistLink> = artists
+        .into_iter()
+        .map(|artist| crate::ArtistLink {
+            id: artist
+                .id
+                .map(|id| id.id().to_string())
+                .unwrap_or_default()
+                .into(),
+            name: artist.name.into(),
+        })
+        .collect();
+    slint::ModelRc::new(slint::VecModel::from(links))
+}
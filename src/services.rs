@@ -1,31 +1,88 @@
+pub mod api;
+pub mod backend;
+pub mod connect;
+#[cfg(unix)]
+pub mod control;
+pub mod error;
+pub mod fake;
+pub mod lyrics;
+#[cfg(test)]
+pub mod mock_web_api;
+#[cfg(target_os = "linux")]
+pub mod mpris;
+pub mod output;
+pub mod quality;
+pub mod queue;
+pub mod remote;
+pub mod retry;
+pub mod settings;
 pub mod spotify;
 
 struct Services {
-    spotify: spotify::SpotifyService,
+    backend: Box<dyn backend::Backend>,
+    settings: std::sync::RwLock<settings::Settings>,
     rt: tokio::runtime::Handle,
-    ui: slint::Weak<crate::MainWindow>,
+    /// Unset in headless mode, where no view model runs.
+    ui: Option<slint::Weak<crate::MainWindow>>,
 }
 
 static SERVICES: std::sync::OnceLock<Services> = std::sync::OnceLock::new();
 
 pub fn init(
-    spotify: spotify::SpotifyService,
+    backend: impl backend::Backend + 'static,
+    settings: settings::Settings,
     rt: tokio::runtime::Handle,
-    ui: slint::Weak<crate::MainWindow>,
+    ui: Option<slint::Weak<crate::MainWindow>>,
 ) {
+    let api_listen = settings
+        .api
+        .enabled
+        .then(|| settings.api_listen())
+        .transpose();
     SERVICES
-        .set(Services { spotify, rt, ui })
+        .set(Services {
+            backend: Box::new(backend),
+            settings: std::sync::RwLock::new(settings),
+            rt,
+            ui,
+        })
         .unwrap_or_else(|_| {
             log::error!("Init must be called only once");
         });
+    self::rt().spawn(self::backend().drive());
+    match api_listen {
+        Ok(Some(listen)) => {
+            self::rt().spawn(async move {
+                if let Err(e) = api::serve(listen).await {
+                    log::error!("Control API stopped: {:#}", e);
+                }
+            });
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("Control API disabled: {:#}", e),
+    }
 }
 
+pub fn backend() -> &'static dyn backend::Backend {
+    SERVICES.get().unwrap().backend.as_ref()
+}
+/// The Spotify service, for what only Spotify offers. Panics on another backend.
 pub fn spotify() -> &'static spotify::SpotifyService {
-    &SERVICES.get().unwrap().spotify
+    backend()
+        .as_spotify()
+        .expect("Only available with the Spotify backend")
+}
+pub fn settings() -> &'static std::sync::RwLock<settings::Settings> {
+    &SERVICES.get().unwrap().settings
 }
 pub fn rt() -> &'static tokio::runtime::Handle {
     &SERVICES.get().unwrap().rt
 }
 pub fn ui_weak() -> &'static slint::Weak<crate::MainWindow> {
-    &SERVICES.get().unwrap().ui
+    SERVICES
+        .get()
+        .unwrap()
+        .ui
+        .as_ref()
+        .expect("No UI in headless mode")
 }
@@ -0,0 +1,729 @@
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::Context;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full, StreamBody, combinators::UnsyncBoxBody};
+use hyper::body::{Frame, Incoming};
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use librespot_core::SpotifyId;
+use librespot_metadata::audio::UniqueFields;
+use librespot_playback::player::PlayerEvent;
+use rspotify::model::SearchType;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use super::backend;
+use super::error::Error;
+use super::queue::RepeatMode;
+use super::settings::ApiListen;
+
+type Body = UnsyncBoxBody<Bytes, Infallible>;
+
+/// Events kept for slow `/events` subscribers before they start missing some.
+const EVENT_BACKLOG: usize = 64;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Track {
+    pub uri: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub duration_ms: u32,
+}
+
+/// The player events `/events` streams, a JSON friendly subset of librespot's.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    TrackChanged { track: Track },
+    Playing { position_ms: u32 },
+    Paused { position_ms: u32 },
+    Stopped,
+    Seeked { position_ms: u32 },
+    PositionChanged { position_ms: u32 },
+    EndOfTrack { uri: String },
+    Unavailable { uri: String },
+    VolumeChanged { percent: u8 },
+    ShuffleChanged { shuffle: bool },
+    RepeatChanged { repeat: &'static str },
+}
+
+impl Event {
+    fn from_player(event: PlayerEvent) -> Option<Event> {
+        let event = match event {
+            PlayerEvent::TrackChanged { audio_item } => {
+                let (artists, album) = match audio_item.unique_fields {
+                    UniqueFields::Track { artists, album, .. } => {
+                        (artists.0.into_iter().map(|a| a.name).collect(), album)
+                    }
+                    UniqueFields::Episode { show_name, .. } => (vec![], show_name),
+                };
+                Event::TrackChanged {
+                    track: Track {
+                        uri: audio_item.uri,
+                        title: audio_item.name,
+                        artists,
+                        album,
+                        duration_ms: audio_item.duration_ms,
+                    },
+                }
+            }
+            PlayerEvent::Playing { position_ms, .. } => Event::Playing { position_ms },
+            PlayerEvent::Paused { position_ms, .. } => Event::Paused { position_ms },
+            PlayerEvent::Stopped { .. } => Event::Stopped,
+            PlayerEvent::Seeked { position_ms, .. }
+            | PlayerEvent::PositionCorrection { position_ms, .. } => Event::Seeked { position_ms },
+            PlayerEvent::PositionChanged { position_ms, .. } => {
+                Event::PositionChanged { position_ms }
+            }
+            PlayerEvent::EndOfTrack { track_id, .. } => Event::EndOfTrack {
+                uri: track_id.to_uri().unwrap_or_default(),
+            },
+            PlayerEvent::Unavailable { track_id, .. } => Event::Unavailable {
+                uri: track_id.to_uri().unwrap_or_default(),
+            },
+            PlayerEvent::VolumeChanged { volume } => Event::VolumeChanged {
+                percent: volume_percent(volume),
+            },
+            PlayerEvent::ShuffleChanged { shuffle } => Event::ShuffleChanged { shuffle },
+            PlayerEvent::RepeatChanged { context, track } => Event::RepeatChanged {
+                repeat: repeat_name(match (context, track) {
+                    (_, true) => RepeatMode::Track,
+                    (true, false) => RepeatMode::Context,
+                    (false, false) => RepeatMode::Off,
+                }),
+            },
+            _ => return None,
+        };
+        Some(event)
+    }
+}
+
+/// Playback as last reported by the player.
+#[derive(Debug, Default)]
+struct Playback {
+    track: Option<Track>,
+    is_playing: bool,
+    position_ms: u32,
+    /// When `position_ms` was reported, to extrapolate it while playing.
+    updated: Option<Instant>,
+}
+
+impl Playback {
+    fn apply(&mut self, event: &Event) {
+        match event {
+            Event::TrackChanged { track } => {
+                self.track = Some(track.clone());
+                self.set_position(0);
+            }
+            Event::Playing { position_ms } => {
+                self.is_playing = true;
+                self.set_position(*position_ms);
+            }
+            Event::Paused { position_ms } => {
+                self.is_playing = false;
+                self.set_position(*position_ms);
+            }
+            Event::Stopped => {
+                self.is_playing = false;
+                self.track = None;
+                self.set_position(0);
+            }
+            Event::Seeked { position_ms } | Event::PositionChanged { position_ms } => {
+                self.set_position(*position_ms)
+            }
+            _ => {}
+        }
+    }
+
+    fn set_position(&mut self, position_ms: u32) {
+        self.position_ms = position_ms;
+        self.updated = Some(Instant::now());
+    }
+
+    fn position_ms(&self) -> u32 {
+        match self.updated {
+            Some(updated) if self.is_playing => {
+                self.position_ms + updated.elapsed().as_millis() as u32
+            }
+            _ => self.position_ms,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    track: Option<Track>,
+    is_playing: bool,
+    position_ms: u32,
+    volume: u8,
+    shuffle: bool,
+    repeat: &'static str,
+    context: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueueState {
+    current: Option<String>,
+    upcoming: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PlayRequest {
+    /// A track to play instead of resuming.
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SeekRequest {
+    position_ms: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct VolumeRequest {
+    percent: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShuffleRequest {
+    shuffle: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct RepeatRequest {
+    repeat: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueueRequest {
+    uri: String,
+}
+
+struct Api {
+    playback: Mutex<Playback>,
+    events: broadcast::Sender<Event>,
+    /// The TCP port requests must name in their `Host`, None on a Unix socket.
+    port: Option<u16>,
+}
+
+/// A failed request, answered with `status` and a JSON error message.
+struct ApiError {
+    status: StatusCode,
+    error: anyhow::Error,
+}
+
+impl ApiError {
+    fn bad_request(error: impl Into<anyhow::Error>) -> ApiError {
+        ApiError {
+            status: StatusCode::BAD_REQUEST,
+            error: error.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        ApiError {
+            status: StatusCode::BAD_GATEWAY,
+            error,
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(error: Error) -> Self {
+        let status = match error {
+            Error::Auth(_) => StatusCode::UNAUTHORIZED,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::PremiumRequired => StatusCode::FORBIDDEN,
+            Error::Network(_) | Error::Playback(_) => StatusCode::BAD_GATEWAY,
+            Error::Cancelled => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        ApiError {
+            status,
+            error: error.into(),
+        }
+    }
+}
+
+/// Serves the control API on `listen` until the runtime shuts down.
+pub async fn serve(listen: ApiListen) -> anyhow::Result<()> {
+    let (events, _) = broadcast::channel(EVENT_BACKLOG);
+    let port = match &listen {
+        ApiListen::Tcp(addr) => Some(addr.port()),
+        ApiListen::Unix(_) => None,
+    };
+    let api = Arc::new(Api {
+        playback: Mutex::new(Playback::default()),
+        events,
+        port,
+    });
+    let follower = api.clone();
+    tokio::spawn(async move {
+        backend()
+            .on_player_event(Box::new(move |event| {
+                if let Some(event) = Event::from_player(event) {
+                    follower.playback.lock().unwrap().apply(&event);
+                    // Nobody listening is fine.
+                    follower.events.send(event).ok();
+                }
+            }))
+            .await;
+    });
+    match listen {
+        ApiListen::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr)
+                .await
+                .with_context(|| format!("Failed to listen on {}", addr))?;
+            log::info!("Control API listening on http://{}", addr);
+            loop {
+                let (stream, _) = listener.accept().await?;
+                serve_connection(TokioIo::new(stream), api.clone());
+            }
+        }
+        #[cfg(unix)]
+        ApiListen::Unix(path) => {
+            let (listener, _guard) = super::control::bind(&path)?;
+            log::info!("Control API listening on {}", path.display());
+            loop {
+                let (stream, _) = listener.accept().await?;
+                serve_connection(TokioIo::new(stream), api.clone());
+            }
+        }
+        #[cfg(not(unix))]
+        ApiListen::Unix(path) => {
+            anyhow::bail!(
+                "Cannot listen on {}, Unix sockets are unsupported",
+                path.display()
+            )
+        }
+    }
+}
+
+fn serve_connection<I>(io: I, api: Arc<Api>)
+where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let service = hyper::service::service_fn(move |request| {
+            let api = api.clone();
+            async move { Ok::<_, Infallible>(handle(&api, request).await) }
+        });
+        if let Err(e) = hyper::server::conn::http1::Builder::new()
+            .serve_connection(io, service)
+            .await
+        {
+            log::debug!("Control API connection closed: {}", e);
+        }
+    });
+}
+
+async fn handle(api: &Api, request: Request<Incoming>) -> Response<Body> {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    log::debug!("Control API {} {}", method, path);
+    let response = match check_caller(&request, api.port) {
+        Ok(()) => route(api, request).await,
+        Err(e) => Err(e),
+    };
+    match response {
+        Ok(response) => response,
+        Err(e) => {
+            log::warn!("Control API {} {} failed: {:#}", method, path, e.error);
+            json_response(
+                e.status,
+                &serde_json::json!({ "error": format!("{:#}", e.error) }),
+            )
+        }
+    }
+}
+
+/// Keeps web pages out. Browsers send an `Origin` with cross-site requests and cannot post JSON
+/// to another origin without a preflight this API never answers, so neither gets through. A
+/// page on a rebound DNS name reads replies as same-origin, but names itself in `Host`.
+fn check_caller<B>(request: &Request<B>, port: Option<u16>) -> Result<(), ApiError> {
+    if let Some(port) = port {
+        let host = request.headers().get(hyper::header::HOST);
+        let loopback = host
+            .and_then(|host| host.to_str().ok())
+            .and_then(|host| host.parse::<hyper::http::uri::Authority>().ok())
+            .is_some_and(|host| is_loopback_host(host.host()) && host.port_u16() == Some(port));
+        if !loopback {
+            return Err(ApiError {
+                status: StatusCode::FORBIDDEN,
+                error: anyhow::anyhow!("Requests for host {:?} are not allowed", host),
+            });
+        }
+    }
+    if let Some(origin) = request.headers().get(hyper::header::ORIGIN) {
+        let loopback = origin
+            .to_str()
+            .ok()
+            .and_then(|origin| origin.parse::<hyper::Uri>().ok())
+            .and_then(|uri| uri.host().map(is_loopback_host))
+            .unwrap_or(false);
+        if !loopback {
+            return Err(ApiError {
+                status: StatusCode::FORBIDDEN,
+                error: anyhow::anyhow!("Requests from {:?} are not allowed", origin),
+            });
+        }
+    }
+    if request.method() == Method::POST {
+        let json = request
+            .headers()
+            .get(hyper::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("application/json"));
+        if !json {
+            return Err(ApiError {
+                status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                error: anyhow::anyhow!("POST requests need Content-Type: application/json"),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+async fn route(api: &Api, request: Request<Incoming>) -> Result<Response<Body>, ApiError> {
+    let backend = backend();
+    let path = request.uri().path().to_string();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method().clone(), segments.as_slice()) {
+        (Method::GET, ["status"]) => Ok(json_response(StatusCode::OK, &status(api))),
+        (Method::GET, ["events"]) => Ok(events(api)),
+        (Method::POST, ["play"]) => {
+            let body: PlayRequest = read_json_or_default(request).await?;
+            match body.uri {
+                Some(uri) => backend.play_queue(vec![parse_track(&uri)?], 0, None),
+                None => backend.play(),
+            }
+            Ok(no_content())
+        }
+        (Method::POST, ["pause"]) => {
+            backend.pause();
+            Ok(no_content())
+        }
+        (Method::POST, ["next"]) => {
+            backend.next();
+            Ok(no_content())
+        }
+        (Method::POST, ["previous"]) => {
+            backend.previous();
+            Ok(no_content())
+        }
+        (Method::POST, ["seek"]) => {
+            let body: SeekRequest = read_json(request).await?;
+            backend.seek(body.position_ms);
+            Ok(no_content())
+        }
+        (Method::POST, ["volume"]) => {
+            let body: VolumeRequest = read_json(request).await?;
+            if body.percent > 100 {
+                return Err(ApiError::bad_request(anyhow::anyhow!(
+                    "percent must be between 0 and 100"
+                )));
+            }
+            backend.set_volume((body.percent as u32 * u16::MAX as u32 / 100) as u16);
+            Ok(no_content())
+        }
+        (Method::POST, ["shuffle"]) => {
+            let body: ShuffleRequest = read_json(request).await?;
+            backend.set_shuffle(body.shuffle);
+            Ok(no_content())
+        }
+        (Method::POST, ["repeat"]) => {
+            let body: RepeatRequest = read_json(request).await?;
+            let repeat = match body.repeat.as_str() {
+                "off" => RepeatMode::Off,
+                "context" => RepeatMode::Context,
+                "track" => RepeatMode::Track,
+                other => {
+                    return Err(ApiError::bad_request(anyhow::anyhow!(
+                        "repeat must be off, context or track, got {other:?}"
+                    )));
+                }
+            };
+            backend.set_repeat(repeat);
+            Ok(no_content())
+        }
+        (Method::GET, ["queue"]) => {
+            let (current, upcoming) = backend.queue();
+            let queue = QueueState {
+                current: current.and_then(|id| id.to_uri().ok()),
+                upcoming: upcoming.iter().filter_map(|id| id.to_uri().ok()).collect(),
+            };
+            Ok(json_response(StatusCode::OK, &queue))
+        }
+        (Method::POST, ["queue"]) => {
+            let body: QueueRequest = read_json(request).await?;
+            backend.enqueue(parse_track(&body.uri)?);
+            Ok(no_content())
+        }
+        (Method::DELETE, ["queue", index]) => {
+            let index: usize = index.parse().map_err(ApiError::bad_request)?;
+            match backend.remove_from_queue(index) {
+                Some(_) => Ok(no_content()),
+                None => Err(ApiError {
+                    status: StatusCode::NOT_FOUND,
+                    error: anyhow::anyhow!("No queued track at {}", index),
+                }),
+            }
+        }
+        (Method::GET, ["search"]) => {
+            let query = request.uri().query().unwrap_or_default();
+            let params: std::collections::HashMap<String, String> =
+                reqwest::Url::parse(&format!("http://localhost/?{}", query))
+                    .map_err(ApiError::bad_request)?
+                    .query_pairs()
+                    .into_owned()
+                    .collect();
+            let q = params
+                .get("q")
+                .filter(|q| !q.trim().is_empty())
+                .ok_or_else(|| ApiError::bad_request(anyhow::anyhow!("q is required")))?;
+            let types = match params.get("type") {
+                Some(types) => types
+                    .split(',')
+                    .map(|t| serde_json::from_value::<SearchType>(t.trim().into()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(ApiError::bad_request)?,
+                None => vec![SearchType::Track],
+            };
+            let limit = parse_param(&params, "limit", 10)?;
+            let offset = parse_param(&params, "offset", 0)?;
+            let spotify = backend.as_spotify().ok_or_else(|| ApiError {
+                status: StatusCode::NOT_IMPLEMENTED,
+                error: anyhow::anyhow!("Search needs the Spotify backend"),
+            })?;
+            let result = spotify.search(q, &types, limit, offset).await?;
+            Ok(json_response(StatusCode::OK, &result))
+        }
+        _ => Err(ApiError {
+            status: StatusCode::NOT_FOUND,
+            error: anyhow::anyhow!("No route for {} {}", request.method(), path),
+        }),
+    }
+}
+
+fn status(api: &Api) -> Status {
+    let backend = backend();
+    let playback = api.playback.lock().unwrap();
+    Status {
+        track: playback.track.clone(),
+        is_playing: playback.is_playing,
+        position_ms: playback.position_ms(),
+        volume: volume_percent(backend.volume()),
+        shuffle: backend.shuffle(),
+        repeat: repeat_name(backend.repeat()),
+        context: backend.current_context(),
+    }
+}
+
+/// Streams player events as server-sent events, one JSON object per event.
+fn events(api: &Api) -> Response<Body> {
+    let events = futures::stream::unfold(api.events.subscribe(), |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    let frame = Frame::data(Bytes::from(format!("data: {}\n\n", data)));
+                    return Some((Ok::<_, Infallible>(frame), events));
+                }
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    log::warn!("Control API subscriber missed {} events", missed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Response::builder()
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .body(BodyExt::boxed_unsync(StreamBody::new(events)))
+        .unwrap()
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(
+    request: Request<Incoming>,
+) -> Result<T, ApiError> {
+    let body = request
+        .into_body()
+        .collect()
+        .await
+        .map_err(ApiError::bad_request)?
+        .to_bytes();
+    serde_json::from_slice(&body).map_err(ApiError::bad_request)
+}
+
+/// Like `read_json`, but an empty body means the defaults.
+async fn read_json_or_default<T: serde::de::DeserializeOwned + Default>(
+    request: Request<Incoming>,
+) -> Result<T, ApiError> {
+    let body = request
+        .into_body()
+        .collect()
+        .await
+        .map_err(ApiError::bad_request)?
+        .to_bytes();
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(&body).map_err(ApiError::bad_request)
+}
+
+fn parse_param(
+    params: &std::collections::HashMap<String, String>,
+    name: &str,
+    default: u32,
+) -> Result<u32, ApiError> {
+    match params.get(name) {
+        Some(value) => value
+            .parse()
+            .with_context(|| format!("Invalid {}", name))
+            .map_err(ApiError::bad_request),
+        None => Ok(default),
+    }
+}
+
+fn parse_track(uri: &str) -> Result<SpotifyId, ApiError> {
+    if !uri.starts_with("spotify:track:") {
+        return Err(ApiError::bad_request(anyhow::anyhow!(
+            "Expected a spotify:track: uri, got {uri:?}"
+        )));
+    }
+    SpotifyId::from_uri(uri).map_err(ApiError::bad_request)
+}
+
+fn json_response(status: StatusCode, value: &impl Serialize) -> Response<Body> {
+    let body = serde_json::to_vec(value).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body)).boxed_unsync())
+        .unwrap()
+}
+
+fn no_content() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .body(Full::new(Bytes::new()).boxed_unsync())
+        .unwrap()
+}
+
+fn volume_percent(volume: u16) -> u8 {
+    (volume as u32 * 100 / u16::MAX as u32) as u8
+}
+
+fn repeat_name(repeat: RepeatMode) -> &'static str {
+    match repeat {
+        RepeatMode::Off => "off",
+        RepeatMode::Context => "context",
+        RepeatMode::Track => "track",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, headers: &[(&str, &str)]) -> Request<()> {
+        let mut builder = Request::builder().method(method).uri("/pause");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    fn status(request: Request<()>) -> Option<StatusCode> {
+        check_caller(&request, None).err().map(|e| e.status)
+    }
+
+    fn tcp_status(host: Option<&str>) -> Option<StatusCode> {
+        let mut builder = Request::builder().uri("/status");
+        if let Some(host) = host {
+            builder = builder.header("host", host);
+        }
+        check_caller(&builder.body(()).unwrap(), Some(8899))
+            .err()
+            .map(|e| e.status)
+    }
+
+    #[test]
+    fn json_posts_from_loopback_are_allowed() {
+        let json = ("content-type", "application/json; charset=utf-8");
+        assert_eq!(status(request(Method::POST, &[json])), None);
+        for origin in [
+            "http://localhost:3000",
+            "http://127.0.0.1:8899",
+            "http://[::1]",
+        ] {
+            assert_eq!(
+                status(request(Method::POST, &[json, ("origin", origin)])),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn posts_need_a_json_content_type() {
+        let unsupported = Some(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert_eq!(status(request(Method::POST, &[])), unsupported);
+        let form = ("content-type", "application/x-www-form-urlencoded");
+        assert_eq!(status(request(Method::POST, &[form])), unsupported);
+        let text = ("content-type", "text/plain");
+        assert_eq!(status(request(Method::POST, &[text])), unsupported);
+        assert_eq!(status(request(Method::GET, &[])), None);
+    }
+
+    #[test]
+    fn foreign_origins_are_rejected() {
+        let json = ("content-type", "application/json");
+        for origin in [
+            "https://example.com",
+            "http://127.0.0.1.example.com",
+            "null",
+        ] {
+            assert_eq!(
+                status(request(Method::POST, &[json, ("origin", origin)])),
+                Some(StatusCode::FORBIDDEN),
+                "{}",
+                origin
+            );
+            assert_eq!(
+                status(request(Method::GET, &[("origin", origin)])),
+                Some(StatusCode::FORBIDDEN)
+            );
+        }
+    }
+
+    #[test]
+    fn tcp_requests_must_name_a_loopback_host() {
+        for host in ["127.0.0.1:8899", "localhost:8899", "[::1]:8899"] {
+            assert_eq!(tcp_status(Some(host)), None, "{}", host);
+        }
+        for host in [
+            Some("evil.example.com:8899"),
+            Some("127.0.0.1"),
+            Some("localhost:80"),
+            Some("127.0.0.1.example.com:8899"),
+            None,
+        ] {
+            assert_eq!(tcp_status(host), Some(StatusCode::FORBIDDEN), "{:?}", host);
+        }
+    }
+}
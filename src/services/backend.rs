@@ -0,0 +1,155 @@
+use anyhow::Context;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use librespot_core::SpotifyId;
+use librespot_metadata::audio::AudioFiles;
+use librespot_playback::player::PlayerEvent;
+use rspotify::model::{PlaylistId, PlaylistItem, SavedTrack, SimplifiedPlaylist};
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+
+use super::error;
+use super::queue::RepeatMode;
+use super::spotify::SpotifyService;
+
+/// Pages of a library list, fetched lazily.
+pub type Pages<'a, T> = BoxStream<'a, error::Result<Vec<T>>>;
+
+/// What the app needs from a music service: logging in, the user's library, playback control
+/// and the player's events. Spotify is the real one, `fake::FakeBackend` runs offline.
+pub trait Backend: Send + Sync {
+    /// Logs in with stored credentials.
+    fn init(&self) -> BoxFuture<'_, error::Result<()>>;
+    /// Logs in interactively.
+    fn auth(&self) -> BoxFuture<'_, error::Result<()>>;
+
+    /// Context URI of the user's liked songs collection.
+    fn liked_songs_uri(&self) -> String;
+    fn user_playlists(&self) -> Pages<'_, SimplifiedPlaylist>;
+    fn playlist_items(&self, id: PlaylistId<'static>) -> Pages<'_, PlaylistItem>;
+    fn saved_tracks(&self) -> Pages<'_, SavedTrack>;
+    fn fetch_cover_art(
+        &self,
+        url: String,
+    ) -> BoxFuture<'_, anyhow::Result<SharedPixelBuffer<Rgba8Pixel>>>;
+
+    fn play(&self);
+    fn pause(&self);
+    fn seek(&self, position_ms: u32);
+    fn next(&self);
+    fn previous(&self);
+    fn volume(&self) -> u16;
+    fn set_volume(&self, volume: u16);
+    fn shuffle(&self) -> bool;
+    fn set_shuffle(&self, shuffle: bool);
+    fn repeat(&self) -> RepeatMode;
+    fn set_repeat(&self, repeat: RepeatMode);
+
+    fn toggle_shuffle(&self) {
+        self.set_shuffle(!self.shuffle());
+    }
+
+    fn cycle_repeat(&self) {
+        self.set_repeat(self.repeat().cycle());
+    }
+
+    /// Replaces the queue and starts playing from `start`.
+    fn play_queue(&self, tracks: Vec<SpotifyId>, start: usize, context_uri: Option<String>);
+
+    /// Starts playing `ids` from `start`, remembering `context_uri` as the source of the queue.
+    fn play_context(
+        &self,
+        context_uri: String,
+        ids: &[String],
+        start: usize,
+    ) -> anyhow::Result<()> {
+        let (tracks, start_at) = parse_ids("track", ids, start)?;
+        log::info!("Playing context {} from {}", context_uri, start_at);
+        self.play_queue(tracks, start_at, Some(context_uri));
+        Ok(())
+    }
+
+    fn load_track(&self, id: String) -> anyhow::Result<()> {
+        let track_id = SpotifyId::from_uri(&format!("spotify:track:{}", id))?;
+        self.play_queue(vec![track_id], 0, None);
+        log::info!("Loaded track {}", id);
+        Ok(())
+    }
+
+    fn enqueue(&self, track_id: SpotifyId);
+    /// The queue: the current item and what plays after it.
+    fn queue(&self) -> (Option<SpotifyId>, Vec<SpotifyId>);
+    fn remove_from_queue(&self, index: usize) -> Option<SpotifyId>;
+    fn current_context(&self) -> Option<String>;
+
+    /// Describes the file the player picks for `files`, when the backend streams files.
+    fn streaming_quality(&self, _files: &AudioFiles) -> Option<String> {
+        None
+    }
+
+    /// Keeps playback going, such as moving on at the end of a track. Runs for as long as the
+    /// backend lives.
+    fn drive(&self) -> BoxFuture<'_, ()>;
+
+    /// Calls `callback` with every player event until the backend shuts down.
+    fn on_player_event<'a>(
+        &'a self,
+        callback: Box<dyn Fn(PlayerEvent) + Send + Sync + 'a>,
+    ) -> BoxFuture<'a, ()>;
+
+    /// The Spotify service behind this backend, for what only Spotify offers such as albums,
+    /// search and Connect devices.
+    fn as_spotify(&self) -> Option<&SpotifyService> {
+        None
+    }
+}
+
+/// Parses base62 `ids` of items of `kind`, returning them with the new index of `start`.
+/// Other invalid ids are skipped, but an invalid `start` is an error rather than playing
+/// something else.
+pub fn parse_ids(
+    kind: &str,
+    ids: &[String],
+    start: usize,
+) -> anyhow::Result<(Vec<SpotifyId>, usize)> {
+    let mut items = Vec::with_capacity(ids.len());
+    let mut start_at = None;
+    for (i, id) in ids.iter().enumerate() {
+        match SpotifyId::from_uri(&format!("spotify:{}:{}", kind, id)) {
+            Ok(item_id) => {
+                if i == start {
+                    start_at = Some(items.len());
+                }
+                items.push(item_id);
+            }
+            Err(e) if i == start => anyhow::bail!("Invalid {} id {}: {}", kind, id, e),
+            Err(e) => log::warn!("Skipping invalid {} id {}: {}", kind, id, e),
+        }
+    }
+    let start_at = start_at.with_context(|| format!("No {} at {}", kind, start))?;
+    Ok((items, start_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID: [&str; 2] = ["4uLU6hMCjMI75M1A2tKUQC", "7GhIk7Il098yCjg4BQjzvb"];
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn invalid_ids_before_start_shift_it() {
+        let (items, start_at) = parse_ids("track", &ids(&["bad", VALID[0], VALID[1]]), 2).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(start_at, 1);
+        assert_eq!(items[start_at].to_base62().unwrap(), VALID[1]);
+    }
+
+    #[test]
+    fn invalid_start_is_an_error() {
+        assert!(parse_ids("track", &ids(&[VALID[0], "bad", VALID[1]]), 1).is_err());
+        assert!(parse_ids("track", &ids(&[VALID[0]]), 1).is_err());
+    }
+}
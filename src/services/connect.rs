@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex};
+
+use librespot_connect::{ConnectConfig, Spirc};
+use librespot_core::{Error, Session, authentication::Credentials};
+use librespot_playback::{
+    mixer::Mixer,
+    player::{Player, PlayerEvent},
+};
+use tokio::sync::watch;
+
+use super::queue::RepeatMode;
+
+#[derive(Debug, Default)]
+struct RemoteState {
+    /// Whether another client is currently playing to us.
+    active: bool,
+    shuffle: bool,
+    repeat: RepeatMode,
+}
+
+/// Spotify Connect receiver, playing on its own `Player` so the connect state machine never
+/// fights the local queue over end of track handling.
+pub struct Receiver {
+    config: ConnectConfig,
+    /// Replaced with one streaming over the new session by `restart`.
+    player: watch::Sender<Arc<Player>>,
+    spirc: Mutex<Option<Arc<Spirc>>>,
+    state: Mutex<RemoteState>,
+}
+
+impl Receiver {
+    pub fn new(config: ConnectConfig, player: Arc<Player>) -> Receiver {
+        Receiver {
+            config,
+            player: watch::Sender::new(player),
+            spirc: Mutex::new(None),
+            state: Mutex::new(RemoteState::default()),
+        }
+    }
+
+    /// The connect player, now and after every restart.
+    pub fn players(&self) -> watch::Receiver<Arc<Player>> {
+        self.player.subscribe()
+    }
+
+    /// Connects `session` and announces the device, running the connect loop on the runtime.
+    pub async fn start(
+        &self,
+        session: Session,
+        credentials: Credentials,
+        mixer: Arc<dyn Mixer>,
+    ) -> Result<(), Error> {
+        if self.spirc.lock().unwrap().is_some() {
+            log::error!("Spotify Connect is already running");
+            return Ok(());
+        }
+        let player = self.player.borrow().clone();
+        let (spirc, task) =
+            Spirc::new(self.config.clone(), session, credentials, player, mixer).await?;
+        *self.spirc.lock().unwrap() = Some(Arc::new(spirc));
+        tokio::spawn(async move {
+            task.await;
+            log::info!("Spotify Connect stopped");
+        });
+        log::info!("Available as Spotify Connect device {}", self.config.name);
+        Ok(())
+    }
+
+    /// Stops the connect loop of a dropped session and starts over on `session`, playing on
+    /// `player` which streams over it. Remote clients have to pick the device again.
+    pub async fn restart(
+        &self,
+        session: Session,
+        credentials: Credentials,
+        player: Arc<Player>,
+        mixer: Arc<dyn Mixer>,
+    ) -> Result<(), Error> {
+        if let Some(spirc) = self.spirc.lock().unwrap().take()
+            && let Err(e) = spirc.shutdown()
+        {
+            log::warn!("Failed to stop Spotify Connect: {}", e);
+        }
+        self.state.lock().unwrap().active = false;
+        self.player.send_replace(player);
+        self.start(session, credentials, mixer).await
+    }
+
+    /// The connect handle while a remote client is driving playback.
+    pub fn remote(&self) -> Option<Arc<Spirc>> {
+        if self.state.lock().unwrap().active {
+            self.spirc.lock().unwrap().clone()
+        } else {
+            None
+        }
+    }
+
+    pub fn shuffle(&self) -> bool {
+        self.state.lock().unwrap().shuffle
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.state.lock().unwrap().repeat
+    }
+
+    /// Hands playback back to the local queue.
+    pub fn release(&self) {
+        if let Some(spirc) = self.remote() {
+            if let Err(e) = spirc.disconnect(true) {
+                log::error!("Failed to leave Spotify Connect session: {}", e);
+            }
+            self.state.lock().unwrap().active = false;
+        }
+    }
+
+    /// Tracks the remote session from the connect player's events, returns true when a remote
+    /// client just took over.
+    pub fn handle_event(&self, event: &PlayerEvent) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *event {
+            PlayerEvent::SessionConnected { .. } => {
+                let took_over = !state.active;
+                state.active = true;
+                return took_over;
+            }
+            PlayerEvent::SessionDisconnected { .. } => state.active = false,
+            PlayerEvent::ShuffleChanged { shuffle } => state.shuffle = shuffle,
+            PlayerEvent::RepeatChanged { context, track } => {
+                state.repeat = match (context, track) {
+                    (_, true) => RepeatMode::Track,
+                    (true, false) => RepeatMode::Context,
+                    (false, false) => RepeatMode::Off,
+                };
+            }
+            _ => {}
+        }
+        false
+    }
+}
@@ -0,0 +1,233 @@
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use librespot_core::SpotifyId;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use super::queue::RepeatMode;
+
+/// A command sent to the control socket, one per line such as "seek 30000".
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Play,
+    Pause,
+    Next,
+    Previous,
+    /// Absolute position in milliseconds.
+    Seek(u32),
+    /// Volume in percent.
+    Volume(u8),
+    Shuffle(bool),
+    Repeat(RepeatMode),
+    /// Plays a single track right away.
+    Load(SpotifyId),
+    /// Appends a track to the queue.
+    Enqueue(SpotifyId),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> anyhow::Result<Command> {
+        let mut words = line.split_whitespace();
+        let name = words.next().context("Empty command")?;
+        let arg = words.next();
+        let command = match name {
+            "play" => Command::Play,
+            "pause" => Command::Pause,
+            "next" => Command::Next,
+            "previous" => Command::Previous,
+            "seek" => Command::Seek(arg.context("seek needs a position in ms")?.parse()?),
+            "volume" => {
+                let volume: u8 = arg.context("volume needs a percentage")?.parse()?;
+                anyhow::ensure!(volume <= 100, "volume must be between 0 and 100");
+                Command::Volume(volume)
+            }
+            "shuffle" => Command::Shuffle(match arg {
+                Some("on") => true,
+                Some("off") => false,
+                _ => anyhow::bail!("shuffle needs on or off"),
+            }),
+            "repeat" => Command::Repeat(match arg {
+                Some("off") => RepeatMode::Off,
+                Some("context") => RepeatMode::Context,
+                Some("track") => RepeatMode::Track,
+                _ => anyhow::bail!("repeat needs off, context or track"),
+            }),
+            "load" => Command::Load(parse_track(arg)?),
+            "enqueue" => Command::Enqueue(parse_track(arg)?),
+            other => anyhow::bail!("Unknown command {other:?}"),
+        };
+        Ok(command)
+    }
+
+    pub fn execute(self) -> anyhow::Result<()> {
+        let backend = super::backend();
+        match self {
+            Command::Play => backend.play(),
+            Command::Pause => backend.pause(),
+            Command::Next => backend.next(),
+            Command::Previous => backend.previous(),
+            Command::Seek(position_ms) => backend.seek(position_ms),
+            Command::Volume(percent) => {
+                backend.set_volume((percent as u32 * u16::MAX as u32 / 100) as u16)
+            }
+            Command::Shuffle(shuffle) => backend.set_shuffle(shuffle),
+            Command::Repeat(repeat) => backend.set_repeat(repeat),
+            Command::Load(track_id) => backend.play_queue(vec![track_id], 0, None),
+            Command::Enqueue(track_id) => backend.enqueue(track_id),
+        }
+        Ok(())
+    }
+}
+
+fn parse_track(arg: Option<&str>) -> anyhow::Result<SpotifyId> {
+    let uri = arg.context("Expected a spotify:track: uri")?;
+    anyhow::ensure!(
+        uri.starts_with("spotify:track:"),
+        "Expected a spotify:track: uri"
+    );
+    Ok(SpotifyId::from_uri(uri)?)
+}
+
+/// Removes a socket left behind by a previous run, which would make bind fail. Anything else
+/// at `path` is a configuration mistake and is left alone, as is a socket another instance
+/// is still listening on.
+pub fn remove_stale_socket(path: &Path) -> anyhow::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to inspect {}", path.display())),
+    };
+    anyhow::ensure!(
+        metadata.file_type().is_socket(),
+        "Refusing to replace {}, it is not a socket",
+        path.display()
+    );
+    anyhow::ensure!(
+        std::os::unix::net::UnixStream::connect(path).is_err(),
+        "Refusing to replace {}, another instance is listening on it",
+        path.display()
+    );
+    std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+}
+
+/// A socket this process bound, removed again when dropped.
+pub struct BoundSocket {
+    path: PathBuf,
+}
+
+impl Drop for BoundSocket {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            log::warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Listens on the Unix socket at `path`, replacing only a stale socket. The guard removes
+/// the socket once the listener is done with it.
+pub fn bind(path: &Path) -> anyhow::Result<(UnixListener, BoundSocket)> {
+    remove_stale_socket(path)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    let guard = BoundSocket {
+        path: path.to_path_buf(),
+    };
+    Ok((listener, guard))
+}
+
+/// Accepts clients on the Unix socket at `path`, answering each command with "ok" or
+/// "error <reason>". The socket is removed when this returns or is cancelled.
+pub async fn serve(path: &Path) -> anyhow::Result<()> {
+    let (listener, _guard) = bind(path)?;
+    log::info!("Listening for commands on {}", path.display());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            if let Err(e) = handle_client(stream).await {
+                log::warn!("Control client failed: {:#}", e);
+            }
+        });
+    }
+}
+
+async fn handle_client(stream: UnixStream) -> anyhow::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        log::info!("Control command: {}", line);
+        let reply = match Command::parse(&line).and_then(Command::execute) {
+            Ok(()) => "ok\n".to_string(),
+            Err(e) => format!("error {:#}\n", e),
+        };
+        write.write_all(reply.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("taan-control-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn stale_socket_is_removed() {
+        let path = scratch("stale.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        remove_stale_socket(&path).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn missing_path_is_fine() {
+        remove_stale_socket(&scratch("missing.sock")).unwrap();
+    }
+
+    #[test]
+    fn other_files_are_kept() {
+        let path = scratch("settings.json");
+        std::fs::write(&path, "{}").unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+    }
+
+    #[test]
+    fn live_socket_is_kept() {
+        let path = scratch("live.sock");
+        let _listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(remove_stale_socket(&path).is_err());
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn bound_socket_is_removed_on_drop() {
+        let path = scratch("bound.sock");
+        let (listener, guard) = bind(&path).unwrap();
+        assert!(path.exists());
+        drop(listener);
+        drop(guard);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn refused_path_is_not_removed() {
+        let path = scratch("refused.json");
+        std::fs::write(&path, "{}").unwrap();
+        assert!(serve(&path).await.is_err());
+        assert!(path.exists());
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt;
+
+use librespot_core::error::ErrorKind;
+use rspotify::{ClientError, http::HttpError};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// What went wrong, sorted by what the user can do about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// Not logged in, or the login was rejected or has expired.
+    Auth(String),
+    /// Spotify could not be reached or answered with something unusable.
+    Network(String),
+    /// Too many requests, Spotify asks to wait `retry_after` seconds.
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    NotFound(String),
+    /// The action needs a Spotify Premium account.
+    PremiumRequired,
+    /// The player could not play, e.g. an unavailable track or a broken audio output.
+    Playback(String),
+    /// Given up on because the app is shutting down.
+    Cancelled,
+}
+
+impl Error {
+    /// Classifies a failed Web API request, reading the message Spotify sent along.
+    pub async fn from_client(error: ClientError) -> Error {
+        let response = match error {
+            ClientError::Http(http) => match *http {
+                HttpError::StatusCode(response) => response,
+                HttpError::Client(e) => return Error::Network(e.to_string()),
+            },
+            ClientError::InvalidToken => return Error::Auth("No access token".into()),
+            e => return Error::Network(e.to_string()),
+        };
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get("Retry-After")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse().ok());
+        // The body is {"error": {"status", "message", "reason"}}, reason only for the player.
+        let body: serde_json::Value = match response.bytes().await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => serde_json::Value::Null,
+        };
+        let message = body["error"]["message"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("HTTP {}", status));
+        match (status, body["error"]["reason"].as_str()) {
+            (401, _) => Error::Auth(message),
+            (403, Some("PREMIUM_REQUIRED")) => Error::PremiumRequired,
+            (403, _) => Error::Auth(message),
+            (404, _) => Error::NotFound(message),
+            (429, _) => Error::RateLimited { retry_after },
+            _ => Error::Network(message),
+        }
+    }
+
+    /// A short name for the kind of error, which the UI picks its wording and icon by.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::Auth(_) => "auth",
+            Error::Network(_) => "network",
+            Error::RateLimited { .. } => "rate-limit",
+            Error::NotFound(_) => "not-found",
+            Error::PremiumRequired => "premium-required",
+            Error::Playback(_) => "playback",
+            Error::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Auth(message) => write!(f, "Login failed: {}", message),
+            Error::Network(message) => write!(f, "Could not reach Spotify: {}", message),
+            Error::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "Too many requests, try again in {}s", secs),
+            Error::RateLimited { retry_after: None } => {
+                write!(f, "Too many requests, try again later")
+            }
+            Error::NotFound(message) => write!(f, "Not found: {}", message),
+            Error::PremiumRequired => write!(f, "This needs Spotify Premium"),
+            Error::Playback(message) => write!(f, "Playback failed: {}", message),
+            Error::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<librespot_core::Error> for Error {
+    fn from(error: librespot_core::Error) -> Self {
+        let message = error.error.to_string();
+        match error.kind {
+            ErrorKind::PermissionDenied if message.contains("Premium") => Error::PremiumRequired,
+            ErrorKind::Unauthenticated | ErrorKind::PermissionDenied => Error::Auth(message),
+            ErrorKind::NotFound => Error::NotFound(message),
+            ErrorKind::ResourceExhausted => Error::RateLimited { retry_after: None },
+            ErrorKind::Unavailable | ErrorKind::DeadlineExceeded | ErrorKind::Aborted => {
+                Error::Network(message)
+            }
+            _ => Error::Playback(message),
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures::future::BoxFuture;
+use futures::{StreamExt, stream};
+use librespot_core::SpotifyId;
+use librespot_metadata::artist::{ArtistWithRole, ArtistsWithRole};
+use librespot_metadata::audio::{AudioFiles, AudioItem, UniqueFields};
+use librespot_playback::player::PlayerEvent;
+use librespot_protocol::metadata::artist_with_role::ArtistRole;
+use rspotify::model::{
+    FullTrack, PlayableItem, PlaylistId, PlaylistItem, PlaylistTracksRef, PublicUser, SavedTrack,
+    SimplifiedAlbum, SimplifiedArtist, SimplifiedPlaylist, Type, UserId,
+};
+use rspotify::prelude::Id;
+use slint::{Rgba8Pixel, SharedPixelBuffer};
+use tokio::sync::broadcast;
+
+use super::backend::{Backend, Pages};
+use super::error::{self, Error};
+use super::queue::{Queue, RepeatMode};
+
+/// Player events kept for subscribers that fall behind.
+const EVENT_BACKLOG: usize = 64;
+/// How often the position is reported while playing, like the real player is configured to.
+const TICK: Duration = Duration::from_secs(1);
+
+/// A backend that runs offline on scripted tracks, without a session or an audio device. It
+/// keeps a real queue and emits the `PlayerEvent`s the player would, so the view models work
+/// against it unchanged.
+pub struct FakeBackend {
+    playlists: Vec<(SimplifiedPlaylist, Vec<FullTrack>)>,
+    saved_tracks: Vec<FullTrack>,
+    tracks: HashMap<SpotifyId, FullTrack>,
+    state: Mutex<State>,
+    events: broadcast::Sender<PlayerEvent>,
+}
+
+#[derive(Default)]
+struct State {
+    queue: Queue,
+    playing: bool,
+    volume: u16,
+    /// When playback last resumed, the queue holds the position at that moment.
+    resumed: Option<Instant>,
+    play_request_id: u64,
+}
+
+impl State {
+    fn position_ms(&self) -> u32 {
+        let elapsed = self
+            .resumed
+            .map(|resumed| resumed.elapsed().as_millis() as u32)
+            .unwrap_or_default();
+        self.queue.position_ms() + elapsed
+    }
+
+    fn set_position_ms(&mut self, position_ms: u32) {
+        self.queue.set_position_ms(position_ms);
+        self.resumed = self.playing.then(Instant::now);
+    }
+}
+
+impl Default for FakeBackend {
+    fn default() -> Self {
+        FakeBackend {
+            playlists: Vec::new(),
+            saved_tracks: Vec::new(),
+            tracks: HashMap::new(),
+            state: Mutex::new(State {
+                volume: u16::MAX / 2,
+                ..Default::default()
+            }),
+            events: broadcast::channel(EVENT_BACKLOG).0,
+        }
+    }
+}
+
+impl FakeBackend {
+    /// A library of a few made up tracks, in one playlist and the liked songs.
+    pub fn sample() -> FakeBackend {
+        let mix = vec![
+            track(
+                &fake_id(1),
+                "First Light",
+                "The Placeholders",
+                "Offline",
+                184_000,
+            ),
+            track(
+                &fake_id(2),
+                "Loopback",
+                "The Placeholders",
+                "Offline",
+                201_000,
+            ),
+            track(
+                &fake_id(3),
+                "Null Route",
+                "Mock Orchestra",
+                "Stubs",
+                243_000,
+            ),
+            track(&fake_id(4), "Fixture", "Mock Orchestra", "Stubs", 167_000),
+        ];
+        let liked = vec![mix[0].clone(), mix[2].clone()];
+        FakeBackend::default()
+            .with_playlist(&fake_id(1), "Offline Mix", mix)
+            .with_saved_tracks(liked)
+    }
+
+    /// Adds a playlist of `tracks` to the library.
+    pub fn with_playlist(mut self, id: &str, name: &str, tracks: Vec<FullTrack>) -> FakeBackend {
+        let playlist = SimplifiedPlaylist {
+            collaborative: false,
+            external_urls: HashMap::new(),
+            href: String::new(),
+            id: PlaylistId::from_id(id.to_string()).expect("Invalid playlist id"),
+            images: Vec::new(),
+            name: name.to_string(),
+            owner: PublicUser {
+                display_name: Some("Offline".to_string()),
+                external_urls: HashMap::new(),
+                followers: None,
+                href: String::new(),
+                id: UserId::from_id("offline").unwrap(),
+                images: Vec::new(),
+            },
+            public: Some(false),
+            snapshot_id: String::new(),
+            tracks: PlaylistTracksRef {
+                href: String::new(),
+                total: tracks.len() as u32,
+            },
+        };
+        self.remember(&tracks);
+        self.playlists.push((playlist, tracks));
+        self
+    }
+
+    /// Adds `tracks` to the liked songs.
+    pub fn with_saved_tracks(mut self, tracks: Vec<FullTrack>) -> FakeBackend {
+        self.remember(&tracks);
+        self.saved_tracks.extend(tracks);
+        self
+    }
+
+    fn remember(&mut self, tracks: &[FullTrack]) {
+        for track in tracks {
+            if let Some(id) = &track.id
+                && let Ok(track_id) = SpotifyId::from_uri(&id.uri())
+            {
+                self.tracks.insert(track_id, track.clone());
+            }
+        }
+    }
+
+    /// Sends `event` to the subscribers as if the player had emitted it.
+    pub fn emit(&self, event: PlayerEvent) {
+        // Nobody listening is fine.
+        self.events.send(event).ok();
+    }
+
+    /// Finishes the current track and moves on like the player does at its end.
+    pub fn end_track(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(track_id) = state.queue.current() else {
+            return;
+        };
+        self.emit(PlayerEvent::EndOfTrack {
+            play_request_id: state.play_request_id,
+            track_id,
+        });
+        match state.queue.advance() {
+            Some(next) => self.load(&mut state, next, 0),
+            None => self.stop(&mut state),
+        }
+    }
+
+    fn load(&self, state: &mut State, track_id: SpotifyId, position_ms: u32) {
+        state.play_request_id += 1;
+        let play_request_id = state.play_request_id;
+        let Some(track) = self.tracks.get(&track_id) else {
+            log::warn!("No scripted track {}", track_id);
+            self.emit(PlayerEvent::Unavailable {
+                play_request_id,
+                track_id,
+            });
+            return;
+        };
+        self.emit(PlayerEvent::TrackChanged {
+            audio_item: Box::new(audio_item(track_id, track)),
+        });
+        state.playing = true;
+        state.set_position_ms(position_ms);
+        self.emit(PlayerEvent::Playing {
+            play_request_id,
+            track_id,
+            position_ms,
+        });
+    }
+
+    fn stop(&self, state: &mut State) {
+        state.playing = false;
+        state.set_position_ms(0);
+        if let Some(track_id) = state.queue.current() {
+            self.emit(PlayerEvent::Stopped {
+                play_request_id: state.play_request_id,
+                track_id,
+            });
+        }
+    }
+}
+
+impl Backend for FakeBackend {
+    fn init(&self) -> BoxFuture<'_, error::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn auth(&self) -> BoxFuture<'_, error::Result<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn liked_songs_uri(&self) -> String {
+        "spotify:user:offline:collection".to_string()
+    }
+
+    fn user_playlists(&self) -> Pages<'_, SimplifiedPlaylist> {
+        let playlists = self.playlists.iter().map(|(p, _)| p.clone()).collect();
+        stream::iter([Ok(playlists)]).boxed()
+    }
+
+    fn playlist_items(&self, id: PlaylistId<'static>) -> Pages<'_, PlaylistItem> {
+        let items = match self.playlists.iter().find(|(p, _)| p.id == id) {
+            Some((_, tracks)) => Ok(tracks
+                .iter()
+                .map(|track| PlaylistItem {
+                    added_at: None,
+                    added_by: None,
+                    is_local: false,
+                    track: Some(PlayableItem::Track(track.clone())),
+                })
+                .collect()),
+            None => Err(Error::NotFound(format!("No scripted playlist {}", id.id()))),
+        };
+        stream::iter([items]).boxed()
+    }
+
+    fn saved_tracks(&self) -> Pages<'_, SavedTrack> {
+        let saved = self
+            .saved_tracks
+            .iter()
+            .map(|track| SavedTrack {
+                added_at: chrono::Utc::now(),
+                track: track.clone(),
+            })
+            .collect();
+        stream::iter([Ok(saved)]).boxed()
+    }
+
+    /// A blank image, there is nothing to download offline.
+    fn fetch_cover_art(
+        &self,
+        _url: String,
+    ) -> BoxFuture<'_, anyhow::Result<SharedPixelBuffer<Rgba8Pixel>>> {
+        Box::pin(async { Ok(SharedPixelBuffer::new(1, 1)) })
+    }
+
+    fn play(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(track_id) = state.queue.current() else {
+            return;
+        };
+        if !state.playing {
+            state.playing = true;
+            state.resumed = Some(Instant::now());
+        }
+        self.emit(PlayerEvent::Playing {
+            play_request_id: state.play_request_id,
+            track_id,
+            position_ms: state.position_ms(),
+        });
+    }
+
+    fn pause(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(track_id) = state.queue.current() else {
+            return;
+        };
+        let position_ms = state.position_ms();
+        state.playing = false;
+        state.set_position_ms(position_ms);
+        self.emit(PlayerEvent::Paused {
+            play_request_id: state.play_request_id,
+            track_id,
+            position_ms,
+        });
+    }
+
+    fn seek(&self, position_ms: u32) {
+        let mut state = self.state.lock().unwrap();
+        let Some(track_id) = state.queue.current() else {
+            return;
+        };
+        state.set_position_ms(position_ms);
+        self.emit(PlayerEvent::Seeked {
+            play_request_id: state.play_request_id,
+            track_id,
+            position_ms,
+        });
+    }
+
+    fn next(&self) {
+        let mut state = self.state.lock().unwrap();
+        match state.queue.skip() {
+            Some(track_id) => self.load(&mut state, track_id, 0),
+            None => log::info!("Reached the end of the queue"),
+        }
+    }
+
+    fn previous(&self) {
+        let mut state = self.state.lock().unwrap();
+        let position_ms = state.position_ms();
+        state.queue.set_position_ms(position_ms);
+        match state.queue.previous() {
+            Some(track_id) => self.load(&mut state, track_id, 0),
+            None => {
+                drop(state);
+                self.seek(0);
+            }
+        }
+    }
+
+    fn volume(&self) -> u16 {
+        self.state.lock().unwrap().volume
+    }
+
+    fn set_volume(&self, volume: u16) {
+        self.state.lock().unwrap().volume = volume;
+        self.emit(PlayerEvent::VolumeChanged { volume });
+    }
+
+    fn shuffle(&self) -> bool {
+        self.state.lock().unwrap().queue.shuffle()
+    }
+
+    fn set_shuffle(&self, shuffle: bool) {
+        self.state.lock().unwrap().queue.set_shuffle(shuffle);
+        self.emit(PlayerEvent::ShuffleChanged { shuffle });
+    }
+
+    fn repeat(&self) -> RepeatMode {
+        self.state.lock().unwrap().queue.repeat()
+    }
+
+    fn set_repeat(&self, repeat: RepeatMode) {
+        self.state.lock().unwrap().queue.set_repeat(repeat);
+        self.emit(PlayerEvent::RepeatChanged {
+            context: repeat != RepeatMode::Off,
+            track: repeat == RepeatMode::Track,
+        });
+    }
+
+    fn play_queue(&self, tracks: Vec<SpotifyId>, start: usize, context_uri: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        match state.queue.set(tracks, start, context_uri) {
+            Some(track_id) => self.load(&mut state, track_id, 0),
+            None => self.stop(&mut state),
+        }
+    }
+
+    fn enqueue(&self, track_id: SpotifyId) {
+        self.state.lock().unwrap().queue.push(track_id);
+    }
+
+    fn queue(&self) -> (Option<SpotifyId>, Vec<SpotifyId>) {
+        let state = self.state.lock().unwrap();
+        (state.queue.current(), state.queue.upcoming())
+    }
+
+    fn remove_from_queue(&self, index: usize) -> Option<SpotifyId> {
+        self.state.lock().unwrap().queue.remove_upcoming(index)
+    }
+
+    fn current_context(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        state.queue.context_uri().map(String::from)
+    }
+
+    /// Reports the position while playing and ends tracks once their duration is up.
+    fn drive(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async {
+            let mut interval = tokio::time::interval(TICK);
+            loop {
+                interval.tick().await;
+                let state = self.state.lock().unwrap();
+                let Some(track_id) = state.queue.current().filter(|_| state.playing) else {
+                    continue;
+                };
+                let position_ms = state.position_ms();
+                let duration_ms = self
+                    .tracks
+                    .get(&track_id)
+                    .map(|track| track.duration.num_milliseconds() as u32)
+                    .unwrap_or_default();
+                if position_ms >= duration_ms {
+                    drop(state);
+                    self.end_track();
+                    continue;
+                }
+                self.emit(PlayerEvent::PositionChanged {
+                    play_request_id: state.play_request_id,
+                    track_id,
+                    position_ms,
+                });
+            }
+        })
+    }
+
+    fn on_player_event<'a>(
+        &'a self,
+        callback: Box<dyn Fn(PlayerEvent) + Send + Sync + 'a>,
+    ) -> BoxFuture<'a, ()> {
+        let mut events = self.events.subscribe();
+        Box::pin(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => callback(event),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// A made up track with id `id`, a base62 track id.
+pub fn track(id: &str, name: &str, artist: &str, album: &str, duration_ms: u32) -> FullTrack {
+    let artist = SimplifiedArtist {
+        external_urls: HashMap::new(),
+        href: None,
+        id: None,
+        name: artist.to_string(),
+    };
+    FullTrack {
+        album: SimplifiedAlbum {
+            album_group: None,
+            album_type: None,
+            artists: vec![artist.clone()],
+            available_markets: Vec::new(),
+            external_urls: HashMap::new(),
+            href: None,
+            id: None,
+            images: Vec::new(),
+            name: album.to_string(),
+            release_date: None,
+            release_date_precision: None,
+            restrictions: None,
+        },
+        artists: vec![artist],
+        available_markets: Vec::new(),
+        disc_number: 1,
+        duration: chrono::TimeDelta::milliseconds(duration_ms as i64),
+        explicit: false,
+        external_ids: HashMap::new(),
+        external_urls: HashMap::new(),
+        href: None,
+        id: Some(rspotify::model::TrackId::from_id(id.to_string()).expect("Invalid track id")),
+        is_local: false,
+        is_playable: Some(true),
+        linked_from: None,
+        restrictions: None,
+        name: name.to_string(),
+        popularity: 0,
+        preview_url: None,
+        track_number: 1,
+        r#type: Type::Track,
+    }
+}
+
+/// A valid base62 id made from `n`.
+fn fake_id(n: u32) -> String {
+    format!("{:0>22}", n)
+}
+
+/// What the player would report for `track` once loaded.
+fn audio_item(track_id: SpotifyId, track: &FullTrack) -> AudioItem {
+    let artists = track
+        .artists
+        .iter()
+        .map(|artist| ArtistWithRole {
+            id: SpotifyId::from_raw(&[0; 16]).unwrap(),
+            name: artist.name.clone(),
+            role: ArtistRole::ARTIST_ROLE_MAIN_ARTIST,
+        })
+        .collect();
+    AudioItem {
+        track_id,
+        uri: track_id.to_uri().unwrap_or_default(),
+        files: AudioFiles(HashMap::new()),
+        name: track.name.clone(),
+        covers: Vec::new(),
+        language: Vec::new(),
+        duration_ms: track.duration.num_milliseconds() as u32,
+        is_explicit: track.explicit,
+        availability: Ok(()),
+        alternatives: None,
+        unique_fields: UniqueFields::Track {
+            artists: ArtistsWithRole(artists),
+            album: track.album.name.clone(),
+            album_artists: track.album.artists.iter().map(|a| a.name.clone()).collect(),
+            popularity: track.popularity as u8,
+            number: track.track_number,
+            disc_number: track.disc_number as u32,
+        },
+    }
+}
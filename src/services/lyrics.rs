@@ -0,0 +1,243 @@
+use std::path::Path;
+
+use librespot_metadata::lyrics::SyncType;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Line {
+    pub start_ms: u32,
+    pub words: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lyrics {
+    /// Whether the lines carry start times, unsynced lyrics are only shown.
+    pub synced: bool,
+    pub lines: Vec<Line>,
+    /// Where the lyrics came from, e.g. "Musixmatch" or "Local file".
+    pub source: String,
+}
+
+impl From<librespot_metadata::Lyrics> for Lyrics {
+    fn from(lyrics: librespot_metadata::Lyrics) -> Self {
+        let inner = lyrics.lyrics;
+        Lyrics {
+            synced: inner.sync_type == SyncType::LineSynced,
+            lines: inner
+                .lines
+                .into_iter()
+                .map(|line| Line {
+                    start_ms: line.start_time_ms.parse().unwrap_or_default(),
+                    words: line.words,
+                })
+                .collect(),
+            source: inner.provider_display_name,
+        }
+    }
+}
+
+/// Parses an LRC file, falling back to plain unsynced lines when it has no timestamps.
+pub fn parse_lrc(text: &str) -> Lyrics {
+    let mut offset_ms = 0i64;
+    let mut lines = vec![];
+    let mut plain = vec![];
+    for raw in text.lines() {
+        let raw = raw.trim();
+        let mut rest = raw;
+        let mut starts = vec![];
+        while let Some((tag, after)) = rest.strip_prefix('[').and_then(|r| r.split_once(']')) {
+            if let Some(ms) = parse_timestamp(tag) {
+                starts.push(ms);
+            } else if let Some(offset) = tag.strip_prefix("offset:") {
+                offset_ms = offset.trim().parse().unwrap_or_default();
+            }
+            rest = after;
+        }
+        if starts.is_empty() {
+            // Metadata tags such as [ar:...] leave nothing behind.
+            if rest == raw && !rest.is_empty() {
+                plain.push(rest.to_string());
+            }
+            continue;
+        }
+        for start in starts {
+            lines.push((start, rest.trim().to_string()));
+        }
+    }
+    if lines.is_empty() {
+        return Lyrics {
+            synced: false,
+            lines: plain
+                .into_iter()
+                .map(|words| Line { start_ms: 0, words })
+                .collect(),
+            source: "Local file".into(),
+        };
+    }
+    lines.sort_by_key(|(start, _)| *start);
+    Lyrics {
+        synced: true,
+        lines: lines
+            .into_iter()
+            .map(|(start, words)| Line {
+                // A positive offset makes the lyrics appear sooner.
+                start_ms: (start - offset_ms).max(0) as u32,
+                words,
+            })
+            .collect(),
+        source: "Local file".into(),
+    }
+}
+
+/// Parses "mm:ss", "mm:ss.xx" or "mm:ss.xxx" into milliseconds.
+fn parse_timestamp(tag: &str) -> Option<i64> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: i64 = minutes.trim().parse().ok()?;
+    let (seconds, fraction) = seconds.split_once(['.', ':']).unwrap_or((seconds, ""));
+    let seconds: i64 = seconds.parse().ok()?;
+    let fraction_ms = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<i64>().ok()? * 100,
+        2 => fraction.parse::<i64>().ok()? * 10,
+        _ => fraction.get(..3)?.parse().ok()?,
+    };
+    Some(minutes * 60_000 + seconds * 1000 + fraction_ms)
+}
+
+/// Looks for "<track id>.lrc" or "<artist> - <title>.lrc" in `dir`.
+pub fn load_local(dir: &Path, id: &str, artist: &str, title: &str) -> Option<Lyrics> {
+    let names = [
+        format!("{}.lrc", id),
+        format!("{} - {}.lrc", artist, title).replace(['/', '\\'], "_"),
+    ];
+    names.iter().find_map(|name| {
+        let text = std::fs::read_to_string(dir.join(name)).ok()?;
+        log::info!("Using local lyrics {}", dir.join(name).display());
+        Some(parse_lrc(&text))
+    })
+}
+
+/// The index of the line being sung at `position_ms`, if any has started yet.
+pub fn current_line(starts: impl Iterator<Item = u32>, position_ms: u32) -> Option<usize> {
+    starts
+        .take_while(|start| *start <= position_ms)
+        .enumerate()
+        .last()
+        .map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(start_ms: u32, words: &str) -> Line {
+        Line {
+            start_ms,
+            words: words.into(),
+        }
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        let cases = [
+            ("01:02", Some(62_000)),
+            ("01:02.5", Some(62_500)),
+            ("01:02.34", Some(62_340)),
+            ("01:02.345", Some(62_345)),
+            ("01:02.3456", Some(62_345)),
+            ("01:02:34", Some(62_340)),
+            (" 1:02", Some(62_000)),
+            ("ar:Someone", None),
+            ("01:xx", None),
+            ("0102", None),
+            ("", None),
+        ];
+        for (tag, expected) in cases {
+            assert_eq!(parse_timestamp(tag), expected, "tag {:?}", tag);
+        }
+    }
+
+    #[test]
+    fn parses_lrc_files() {
+        let cases: [(&str, bool, Vec<Line>); 7] = [
+            (
+                "[00:01.00]One\n[00:02.50]Two",
+                true,
+                vec![line(1000, "One"), line(2500, "Two")],
+            ),
+            (
+                "[00:05.00][00:01.00]Chorus\n[00:03.00]Verse",
+                true,
+                vec![
+                    line(1000, "Chorus"),
+                    line(3000, "Verse"),
+                    line(5000, "Chorus"),
+                ],
+            ),
+            (
+                "[ar:Artist]\n[ti:Title]\n[00:01.00]One",
+                true,
+                vec![line(1000, "One")],
+            ),
+            (
+                "[offset:500]\n[00:01.00]One\n[00:00.20]Zero",
+                true,
+                vec![line(0, "Zero"), line(500, "One")],
+            ),
+            (
+                "[offset:-250]\n[00:01.00]One",
+                true,
+                vec![line(1250, "One")],
+            ),
+            (
+                "[00:01.00]\n[00:02.00]After a break",
+                true,
+                vec![line(1000, ""), line(2000, "After a break")],
+            ),
+            (
+                "[ar:Artist]\nFirst line\n[broken\nSecond line",
+                false,
+                vec![
+                    line(0, "First line"),
+                    line(0, "[broken"),
+                    line(0, "Second line"),
+                ],
+            ),
+        ];
+        for (text, synced, lines) in cases {
+            let lyrics = parse_lrc(text);
+            assert_eq!(lyrics.synced, synced, "lrc {:?}", text);
+            assert_eq!(lyrics.lines, lines, "lrc {:?}", text);
+            assert_eq!(lyrics.source, "Local file");
+        }
+    }
+
+    #[test]
+    fn malformed_offset_is_ignored() {
+        let lyrics = parse_lrc("[offset:soon]\n[00:01.00]One");
+        assert_eq!(lyrics.lines, vec![line(1000, "One")]);
+    }
+
+    #[test]
+    fn finds_the_current_line() {
+        let starts = [1000, 2000, 2000, 5000];
+        let cases = [
+            (0, None),
+            (999, None),
+            (1000, Some(0)),
+            (1999, Some(0)),
+            (2000, Some(2)),
+            (4999, Some(2)),
+            (5000, Some(3)),
+            (u32::MAX, Some(3)),
+        ];
+        for (position_ms, expected) in cases {
+            assert_eq!(
+                current_line(starts.into_iter(), position_ms),
+                expected,
+                "position {}",
+                position_ms
+            );
+        }
+        assert_eq!(current_line(std::iter::empty(), 1000), None);
+    }
+}
@@ -0,0 +1,307 @@
+//! An in-process stand-in for the Spotify Web API, so the pagination, token refresh and rate
+//! limit handling of `SpotifyService` can run without a network. Point
+//! `network.web_api_url` at `MockWebApi::base_url` and script failures with `fail_next`.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::body::Incoming;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rspotify::model::{
+    FullTrack, Page, PlayableItem, PlaylistItem, SavedTrack, SimplifiedPlaylist,
+};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio_util::sync::CancellationToken;
+
+use super::backend::Backend;
+use super::fake::{self, FakeBackend};
+
+/// Page size when the request has no limit, the Web API's own default.
+const DEFAULT_LIMIT: usize = 20;
+
+/// A scripted error answer, served instead of the next request's real one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub status: u16,
+    /// Seconds sent in the `Retry-After` header.
+    pub retry_after: Option<u64>,
+}
+
+impl Failure {
+    /// The answer to an expired access token.
+    pub fn unauthorized() -> Failure {
+        Failure {
+            status: 401,
+            retry_after: None,
+        }
+    }
+
+    pub fn rate_limited(retry_after: u64) -> Failure {
+        Failure {
+            status: 429,
+            retry_after: Some(retry_after),
+        }
+    }
+
+    /// A 5xx answer such as 500, 502 or 503.
+    pub fn server_error(status: u16) -> Failure {
+        Failure {
+            status,
+            retry_after: None,
+        }
+    }
+}
+
+/// A request the server got, for checking what the client sent.
+#[derive(Debug, Clone)]
+pub struct Received {
+    pub method: Method,
+    /// Path and query, e.g. "/v1/me/tracks?limit=50&offset=0".
+    pub uri: String,
+    pub authorization: Option<String>,
+}
+
+#[derive(Default)]
+struct Library {
+    playlists: Vec<SimplifiedPlaylist>,
+    playlist_items: HashMap<String, Vec<PlaylistItem>>,
+    saved_tracks: Vec<SavedTrack>,
+    failures: VecDeque<Failure>,
+    received: Vec<Received>,
+}
+
+/// Serves canned playlists and saved tracks on a loopback port until dropped.
+pub struct MockWebApi {
+    addr: SocketAddr,
+    library: Arc<Mutex<Library>>,
+    shutdown: CancellationToken,
+}
+
+impl MockWebApi {
+    /// Starts serving the library of `FakeBackend::sample` on a free port.
+    pub async fn start() -> anyhow::Result<MockWebApi> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let api = MockWebApi {
+            addr,
+            library: Arc::new(Mutex::new(Library::default())),
+            shutdown: CancellationToken::new(),
+        };
+        api.load(&FakeBackend::sample()).await?;
+        let library = api.library.clone();
+        let shutdown = api.shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            log::warn!("Mock Web API stopped accepting: {}", e);
+                            return;
+                        }
+                    },
+                    _ = shutdown.cancelled() => return,
+                };
+                let library = library.clone();
+                tokio::spawn(async move {
+                    let service = hyper::service::service_fn(move |request| {
+                        let response = handle(&library, request);
+                        async move { Ok::<_, Infallible>(response) }
+                    });
+                    hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                        .ok();
+                });
+            }
+        });
+        Ok(api)
+    }
+
+    /// What to set `network.web_api_url` to.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1/", self.addr)
+    }
+
+    /// Replaces the library with the playlists and saved tracks of `backend`.
+    pub async fn load(&self, backend: &dyn Backend) -> anyhow::Result<()> {
+        let playlists = collect(backend.user_playlists()).await?;
+        let mut playlist_items = HashMap::new();
+        for playlist in &playlists {
+            let items = collect(backend.playlist_items(playlist.id.clone())).await?;
+            playlist_items.insert(rspotify::prelude::Id::id(&playlist.id).to_string(), items);
+        }
+        let saved_tracks = collect(backend.saved_tracks()).await?;
+        let mut library = self.library.lock().unwrap();
+        library.playlists = playlists;
+        library.playlist_items = playlist_items;
+        library.saved_tracks = saved_tracks;
+        Ok(())
+    }
+
+    /// Makes the liked songs `count` generated tracks, enough for several pages.
+    pub fn set_saved_track_count(&self, count: usize) {
+        let saved_tracks = (0..count)
+            .map(|i| SavedTrack {
+                added_at: chrono::Utc::now(),
+                track: numbered_track(i),
+            })
+            .collect();
+        self.library.lock().unwrap().saved_tracks = saved_tracks;
+    }
+
+    /// Makes playlist `id` hold `count` generated tracks.
+    pub fn set_playlist_item_count(&self, id: &str, count: usize) {
+        let items = (0..count)
+            .map(|i| PlaylistItem {
+                added_at: None,
+                added_by: None,
+                is_local: false,
+                track: Some(PlayableItem::Track(numbered_track(i))),
+            })
+            .collect();
+        self.library
+            .lock()
+            .unwrap()
+            .playlist_items
+            .insert(id.to_string(), items);
+    }
+
+    /// Answers the next request with `failure`, after the ones already scripted.
+    pub fn fail_next(&self, failure: Failure) {
+        self.library.lock().unwrap().failures.push_back(failure);
+    }
+
+    /// Every request so far, oldest first.
+    pub fn received(&self) -> Vec<Received> {
+        self.library.lock().unwrap().received.clone()
+    }
+}
+
+impl Drop for MockWebApi {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn collect<T>(pages: super::backend::Pages<'_, T>) -> anyhow::Result<Vec<T>> {
+    use futures::TryStreamExt;
+    let pages: Vec<Vec<T>> = pages.try_collect().await?;
+    Ok(pages.into_iter().flatten().collect())
+}
+
+fn numbered_track(i: usize) -> FullTrack {
+    fake::track(
+        &format!("{:0>22}", i + 1),
+        &format!("Track {}", i + 1),
+        "Mock Orchestra",
+        "Pages",
+        180_000,
+    )
+}
+
+fn handle(library: &Mutex<Library>, request: Request<Incoming>) -> Response<Full<Bytes>> {
+    let mut library = library.lock().unwrap();
+    let uri = request.uri().clone();
+    library.received.push(Received {
+        method: request.method().clone(),
+        uri: uri.to_string(),
+        authorization: request
+            .headers()
+            .get("Authorization")
+            .and_then(|v| v.to_str().ok())
+            .map(String::from),
+    });
+    if let Some(failure) = library.failures.pop_front() {
+        return failure_response(&failure);
+    }
+    let (limit, offset) = paging(uri.query().unwrap_or_default());
+    let path = uri.path();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method(), segments.as_slice()) {
+        (&Method::GET, ["v1", "me", "playlists"]) => {
+            page_response(path, &library.playlists, limit, offset)
+        }
+        (&Method::GET, ["v1", "me", "tracks"]) => {
+            page_response(path, &library.saved_tracks, limit, offset)
+        }
+        (&Method::GET, ["v1", "playlists", id, "tracks"]) => {
+            match library.playlist_items.get(*id) {
+                Some(items) => page_response(path, items, limit, offset),
+                None => error_response(StatusCode::NOT_FOUND, "Invalid playlist Id"),
+            }
+        }
+        _ => error_response(StatusCode::NOT_FOUND, "Service not found"),
+    }
+}
+
+/// The `limit` and `offset` query parameters.
+fn paging(query: &str) -> (usize, usize) {
+    let mut limit = DEFAULT_LIMIT;
+    let mut offset = 0;
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        match key {
+            "limit" => limit = value.parse().unwrap_or(limit),
+            "offset" => offset = value.parse().unwrap_or(offset),
+            _ => {}
+        }
+    }
+    (limit, offset)
+}
+
+fn page_response<T: Serialize + DeserializeOwned + Clone>(
+    path: &str,
+    items: &[T],
+    limit: usize,
+    offset: usize,
+) -> Response<Full<Bytes>> {
+    let link = |offset: usize| format!("{}?offset={}&limit={}", path, offset, limit);
+    let end = (offset + limit).min(items.len());
+    let page = Page {
+        href: link(offset),
+        items: items.get(offset..end).unwrap_or_default().to_vec(),
+        limit: limit as u32,
+        next: (end < items.len()).then(|| link(end)),
+        offset: offset as u32,
+        previous: (offset > 0).then(|| link(offset.saturating_sub(limit))),
+        total: items.len() as u32,
+    };
+    json_response(StatusCode::OK, &page)
+}
+
+fn failure_response(failure: &Failure) -> Response<Full<Bytes>> {
+    let status = StatusCode::from_u16(failure.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let mut response = error_response(status, status.canonical_reason().unwrap_or("Error"));
+    if let Some(retry_after) = failure.retry_after {
+        response
+            .headers_mut()
+            .insert("Retry-After", retry_after.into());
+    }
+    response
+}
+
+/// An error in the Web API's format.
+fn error_response(status: StatusCode, message: &str) -> Response<Full<Bytes>> {
+    let body = serde_json::json!({
+        "error": { "status": status.as_u16(), "message": message }
+    });
+    json_response(status, &body)
+}
+
+fn json_response(status: StatusCode, value: &impl Serialize) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        // The client caches responses, which would hide scripted failures.
+        .header("Cache-Control", "no-store")
+        .body(Full::new(Bytes::from(
+            serde_json::to_vec(value).unwrap_or_default(),
+        )))
+        .unwrap()
+}
@@ -0,0 +1,619 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use tokio::sync::mpsc;
+use zbus::object_server::SignalEmitter;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+use super::queue::RepeatMode;
+
+pub const BUS_NAME: &str = "org.mpris.MediaPlayer2.taan";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+/// Track ids are object paths, MPRIS reserves this one for "no track".
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// A player control requested by an MPRIS client such as media keys or `playerctl`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Play,
+    Pause,
+    PlayPause,
+    Stop,
+    Next,
+    Previous,
+    /// Absolute position in milliseconds.
+    Seek(u32),
+    /// Volume from 0.0 to 1.0.
+    Volume(f64),
+    Shuffle(bool),
+    Repeat(RepeatMode),
+    /// A "spotify:track:..." uri to play.
+    OpenUri(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaybackStatus {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl PlaybackStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            PlaybackStatus::Playing => "Playing",
+            PlaybackStatus::Paused => "Paused",
+            PlaybackStatus::Stopped => "Stopped",
+        }
+    }
+}
+
+/// What MPRIS clients show for the playing item.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metadata {
+    /// Base62 Spotify id of the track or episode.
+    pub id: String,
+    pub title: String,
+    pub artists: Vec<String>,
+    pub album: String,
+    pub duration_ms: u32,
+    pub art_url: Option<String>,
+}
+
+impl Metadata {
+    fn track_path(&self) -> OwnedObjectPath {
+        let path = if self.id.is_empty() {
+            NO_TRACK.to_string()
+        } else {
+            format!("/com/meghdip/taan/track/{}", self.id)
+        };
+        ObjectPath::try_from(path)
+            .unwrap_or_else(|_| ObjectPath::from_static_str_unchecked(NO_TRACK))
+            .into()
+    }
+
+    fn to_map(&self) -> HashMap<String, OwnedValue> {
+        let mut map = HashMap::new();
+        let mut insert = |key: &str, value: Value<'_>| {
+            if let Ok(value) = value.try_to_owned() {
+                map.insert(key.to_string(), value);
+            }
+        };
+        insert("mpris:trackid", Value::from(self.track_path()));
+        if self.id.is_empty() {
+            return map;
+        }
+        insert("mpris:length", Value::from(self.duration_ms as i64 * 1000));
+        insert("xesam:title", Value::from(self.title.as_str()));
+        insert("xesam:artist", Value::from(self.artists.clone()));
+        insert("xesam:album", Value::from(self.album.as_str()));
+        if let Some(url) = &self.art_url {
+            insert("mpris:artUrl", Value::from(url.as_str()));
+        }
+        map
+    }
+}
+
+/// The org.mpris.MediaPlayer2 interface.
+struct Root {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2")]
+impl Root {
+    fn raise(&self) {}
+
+    fn quit(&self) {
+        self.commands.send(Command::Quit).ok();
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> &str {
+        "Taan"
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        vec!["spotify".into()]
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// The org.mpris.MediaPlayer2.Player interface.
+struct Player {
+    commands: mpsc::UnboundedSender<Command>,
+    status: PlaybackStatus,
+    metadata: Metadata,
+    /// The position when it was last reported, at `position_at`.
+    position_ms: u32,
+    position_at: Instant,
+    volume: f64,
+    shuffle: bool,
+    repeat: RepeatMode,
+}
+
+impl Player {
+    /// The reported position plus the time played since, clients poll it during playback.
+    fn current_position_ms(&self) -> u32 {
+        if self.status != PlaybackStatus::Playing {
+            return self.position_ms;
+        }
+        let elapsed = self.position_at.elapsed().as_millis() as u32;
+        let position = self.position_ms.saturating_add(elapsed);
+        match self.metadata.duration_ms {
+            0 => position,
+            duration => position.min(duration),
+        }
+    }
+
+    fn set_position_ms(&mut self, position_ms: u32) {
+        self.position_ms = position_ms;
+        self.position_at = Instant::now();
+    }
+}
+
+#[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+impl Player {
+    fn next(&self) {
+        self.commands.send(Command::Next).ok();
+    }
+
+    fn previous(&self) {
+        self.commands.send(Command::Previous).ok();
+    }
+
+    fn pause(&self) {
+        self.commands.send(Command::Pause).ok();
+    }
+
+    fn play_pause(&self) {
+        self.commands.send(Command::PlayPause).ok();
+    }
+
+    fn stop(&self) {
+        self.commands.send(Command::Stop).ok();
+    }
+
+    fn play(&self) {
+        self.commands.send(Command::Play).ok();
+    }
+
+    /// Seeks `offset` microseconds forwards or backwards from the current position.
+    fn seek(&self, offset: i64) {
+        let position = self.current_position_ms() as i64 + offset / 1000;
+        if position > self.metadata.duration_ms as i64 {
+            self.commands.send(Command::Next).ok();
+            return;
+        }
+        self.commands
+            .send(Command::Seek(position.max(0) as u32))
+            .ok();
+    }
+
+    /// Seeks to `position` microseconds, ignored if `track_id` is no longer playing.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+        if track_id.as_str() != self.metadata.track_path().as_str()
+            || !(0..=self.metadata.duration_ms as i64 * 1000).contains(&position)
+        {
+            return;
+        }
+        self.commands
+            .send(Command::Seek((position / 1000) as u32))
+            .ok();
+    }
+
+    fn open_uri(&self, uri: String) {
+        self.commands.send(Command::OpenUri(uri)).ok();
+    }
+
+    #[zbus(signal)]
+    async fn seeked(emitter: &SignalEmitter<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> &str {
+        self.status.as_str()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> &str {
+        match self.repeat {
+            RepeatMode::Off => "None",
+            RepeatMode::Context => "Playlist",
+            RepeatMode::Track => "Track",
+        }
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&mut self, status: String) -> zbus::fdo::Result<()> {
+        let repeat = match status.as_str() {
+            "None" => RepeatMode::Off,
+            "Playlist" => RepeatMode::Context,
+            "Track" => RepeatMode::Track,
+            other => {
+                return Err(zbus::fdo::Error::InvalidArgs(format!(
+                    "Unknown loop status {other}"
+                )));
+            }
+        };
+        self.commands.send(Command::Repeat(repeat)).ok();
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, _rate: f64) {}
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, shuffle: bool) {
+        self.commands.send(Command::Shuffle(shuffle)).ok();
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.metadata.to_map()
+    }
+
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.volume
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) {
+        self.commands
+            .send(Command::Volume(volume.clamp(0.0, 1.0)))
+            .ok();
+    }
+
+    /// Clients read the position when they need it, so changes are not signalled.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.current_position_ms() as i64 * 1000
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        1.0
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        !self.metadata.id.is_empty()
+    }
+
+    #[zbus(property(emits_changed_signal = "const"))]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+/// An MPRIS2 server that exposes playback to the desktop.
+#[derive(Clone)]
+pub struct Mpris {
+    connection: zbus::Connection,
+}
+
+impl Mpris {
+    /// Serves MPRIS on the session bus, sending the controls of clients to `commands`.
+    pub async fn start(commands: mpsc::UnboundedSender<Command>) -> anyhow::Result<Mpris> {
+        let connection = zbus::Connection::session().await?;
+        Mpris::serve(connection, commands).await
+    }
+
+    /// Serves MPRIS on `connection`, which may also be a private bus.
+    pub async fn serve(
+        connection: zbus::Connection,
+        commands: mpsc::UnboundedSender<Command>,
+    ) -> anyhow::Result<Mpris> {
+        let server = connection.object_server();
+        server
+            .at(
+                OBJECT_PATH,
+                Root {
+                    commands: commands.clone(),
+                },
+            )
+            .await?;
+        server
+            .at(
+                OBJECT_PATH,
+                Player {
+                    commands,
+                    status: PlaybackStatus::Stopped,
+                    metadata: Metadata::default(),
+                    position_ms: 0,
+                    position_at: Instant::now(),
+                    volume: 1.0,
+                    shuffle: false,
+                    repeat: RepeatMode::Off,
+                },
+            )
+            .await?;
+        // Peer-to-peer connections have no bus to own a name on.
+        if connection.unique_name().is_none() {
+            return Ok(Mpris { connection });
+        }
+        // A second instance is still reachable under its unique name.
+        if let Err(e) = connection.request_name(BUS_NAME).await {
+            let instance = format!("{}.instance{}", BUS_NAME, std::process::id());
+            log::warn!("Could not own {}, using {}: {}", BUS_NAME, instance, e);
+            connection.request_name(instance).await?;
+        }
+        Ok(Mpris { connection })
+    }
+
+    async fn player(&self) -> zbus::Result<zbus::object_server::InterfaceRef<Player>> {
+        self.connection
+            .object_server()
+            .interface::<_, Player>(OBJECT_PATH)
+            .await
+    }
+
+    pub async fn set_metadata(&self, metadata: Metadata) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        let mut iface = player.get_mut().await;
+        iface.metadata = metadata;
+        iface.set_position_ms(0);
+        iface.metadata_changed(player.signal_emitter()).await?;
+        iface.can_seek_changed(player.signal_emitter()).await?;
+        Ok(())
+    }
+
+    pub async fn set_playback_status(&self, status: PlaybackStatus) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        let mut iface = player.get_mut().await;
+        if iface.status != status {
+            // Freeze or restart the clock at the position reached so far.
+            let position_ms = iface.current_position_ms();
+            iface.set_position_ms(position_ms);
+            iface.status = status;
+            iface
+                .playback_status_changed(player.signal_emitter())
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_position(&self, position_ms: u32) -> anyhow::Result<()> {
+        self.player()
+            .await?
+            .get_mut()
+            .await
+            .set_position_ms(position_ms);
+        Ok(())
+    }
+
+    /// Records a jump in the position that clients could not extrapolate.
+    pub async fn seeked(&self, position_ms: u32) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        player.get_mut().await.set_position_ms(position_ms);
+        Player::seeked(player.signal_emitter(), position_ms as i64 * 1000).await?;
+        Ok(())
+    }
+
+    /// Volume from 0.0 to 1.0.
+    pub async fn set_volume(&self, volume: f64) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        let mut iface = player.get_mut().await;
+        iface.volume = volume;
+        iface.volume_changed(player.signal_emitter()).await?;
+        Ok(())
+    }
+
+    pub async fn set_shuffle(&self, shuffle: bool) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        let mut iface = player.get_mut().await;
+        iface.shuffle = shuffle;
+        iface.shuffle_changed(player.signal_emitter()).await?;
+        Ok(())
+    }
+
+    pub async fn set_repeat(&self, repeat: RepeatMode) -> anyhow::Result<()> {
+        let player = self.player().await?;
+        let mut iface = player.get_mut().await;
+        iface.repeat = repeat;
+        iface.loop_status_changed(player.signal_emitter()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use zbus::zvariant::OwnedValue;
+
+    use super::*;
+
+    const PLAYER: &str = "org.mpris.MediaPlayer2.Player";
+
+    /// Serves MPRIS on one end of a socket pair, returning the client end.
+    async fn serve() -> (Mpris, zbus::Connection, mpsc::UnboundedReceiver<Command>) {
+        let (server, client) = std::os::unix::net::UnixStream::pair().unwrap();
+        let (commands_tx, commands) = mpsc::unbounded_channel();
+        // Serving from the builder makes it wait until the object server listens, a call made
+        // before that would be dropped. `Mpris::serve` then finds Root already there.
+        let root = Root {
+            commands: commands_tx.clone(),
+        };
+        let (server, client) = futures::try_join!(
+            zbus::connection::Builder::unix_stream(server)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at(OBJECT_PATH, root)
+                .unwrap()
+                .build(),
+            zbus::connection::Builder::unix_stream(client).p2p().build(),
+        )
+        .unwrap();
+        let mpris = Mpris::serve(server, commands_tx).await.unwrap();
+        (mpris, client, commands)
+    }
+
+    async fn call<B>(client: &zbus::Connection, method: &str, body: &B)
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        client
+            .call_method(None::<&str>, OBJECT_PATH, Some(PLAYER), method, body)
+            .await
+            .unwrap();
+    }
+
+    async fn property(client: &zbus::Connection, name: &str) -> OwnedValue {
+        client
+            .call_method(
+                None::<&str>,
+                OBJECT_PATH,
+                Some("org.freedesktop.DBus.Properties"),
+                "Get",
+                &(PLAYER, name),
+            )
+            .await
+            .unwrap()
+            .body()
+            .deserialize::<OwnedValue>()
+            .unwrap()
+    }
+
+    fn track() -> Metadata {
+        Metadata {
+            id: "4uLU6hMCjMI75M1A2tKUQC".into(),
+            title: "First Light".into(),
+            artists: vec!["The Placeholders".into()],
+            album: "Offline".into(),
+            duration_ms: 184_000,
+            art_url: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn reports_playback_status() {
+        let (mpris, client, _commands) = serve().await;
+        let status = property(&client, "PlaybackStatus").await;
+        assert_eq!(String::try_from(status).unwrap(), "Stopped");
+        mpris
+            .set_playback_status(PlaybackStatus::Playing)
+            .await
+            .unwrap();
+        let status = property(&client, "PlaybackStatus").await;
+        assert_eq!(String::try_from(status).unwrap(), "Playing");
+    }
+
+    #[tokio::test]
+    async fn reports_metadata() {
+        let (mpris, client, _commands) = serve().await;
+        mpris.set_metadata(track()).await.unwrap();
+        let metadata: HashMap<String, OwnedValue> =
+            property(&client, "Metadata").await.try_into().unwrap();
+        let text = |key: &str| String::try_from(metadata[key].try_clone().unwrap()).unwrap();
+        assert_eq!(text("xesam:title"), "First Light");
+        assert_eq!(text("xesam:album"), "Offline");
+        assert_eq!(
+            i64::try_from(metadata["mpris:length"].try_clone().unwrap()).unwrap(),
+            184_000_000
+        );
+        let track_id = OwnedObjectPath::try_from(metadata["mpris:trackid"].try_clone().unwrap());
+        assert_eq!(
+            track_id.unwrap().as_str(),
+            "/com/meghdip/taan/track/4uLU6hMCjMI75M1A2tKUQC"
+        );
+    }
+
+    #[tokio::test]
+    async fn forwards_play_pause_and_seek() {
+        let (mpris, client, mut commands) = serve().await;
+        mpris.set_metadata(track()).await.unwrap();
+        mpris.set_position(10_000).await.unwrap();
+        call(&client, "PlayPause", &()).await;
+        assert_eq!(commands.recv().await, Some(Command::PlayPause));
+        call(&client, "Seek", &5_000_000i64).await;
+        assert_eq!(commands.recv().await, Some(Command::Seek(15_000)));
+        call(&client, "Seek", &-60_000_000i64).await;
+        assert_eq!(commands.recv().await, Some(Command::Seek(0)));
+        call(&client, "Seek", &600_000_000i64).await;
+        assert_eq!(commands.recv().await, Some(Command::Next));
+    }
+
+    #[tokio::test]
+    async fn position_advances_only_while_playing() {
+        let (mpris, client, _commands) = serve().await;
+        mpris.set_metadata(track()).await.unwrap();
+        mpris.set_position(1_000).await.unwrap();
+        let position = |value: OwnedValue| i64::try_from(value).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(position(property(&client, "Position").await), 1_000_000);
+
+        mpris
+            .set_playback_status(PlaybackStatus::Playing)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let playing = position(property(&client, "Position").await);
+        assert!(playing >= 1_100_000, "position {}", playing);
+
+        mpris
+            .set_playback_status(PlaybackStatus::Paused)
+            .await
+            .unwrap();
+        let paused = position(property(&client, "Position").await);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(position(property(&client, "Position").await), paused);
+    }
+}
@@ -0,0 +1,213 @@
+use std::{
+    panic::AssertUnwindSafe,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use librespot_playback::{
+    SAMPLES_PER_SECOND,
+    audio_backend::{self, Sink, SinkResult},
+    config::AudioFormat,
+    convert::Converter,
+    decoder::AudioPacket,
+};
+use tokio::sync::broadcast;
+
+const RODIO_BACKEND: &str = "rodio";
+
+/// Names of the audio backends compiled into librespot, the default first.
+pub fn backends() -> Vec<String> {
+    audio_backend::BACKENDS
+        .iter()
+        .map(|(name, _)| name.to_string())
+        .collect()
+}
+
+/// Output devices of `backend`, empty when the backend can only use its default device.
+pub fn devices(backend: Option<&str>) -> Vec<String> {
+    let backend = backend.unwrap_or(audio_backend::BACKENDS[0].0);
+    if backend != RODIO_BACKEND {
+        return vec![];
+    }
+    use cpal::traits::{DeviceTrait, HostTrait};
+    match cpal::default_host().output_devices() {
+        Ok(devices) => devices.filter_map(|d| d.name().ok()).collect(),
+        Err(e) => {
+            log::error!("Failed to list output devices: {}", e);
+            vec![]
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct OutputConfig {
+    backend: Option<String>,
+    device: Option<String>,
+    format: AudioFormat,
+    /// Bumped on every change so running sinks know to rebuild.
+    generation: u64,
+}
+
+/// The audio output the player writes to, switchable while playing.
+pub struct Output {
+    config: Mutex<OutputConfig>,
+    errors: broadcast::Sender<String>,
+}
+
+impl Output {
+    pub fn new(backend: Option<String>, device: Option<String>, format: AudioFormat) -> Output {
+        Output {
+            config: Mutex::new(OutputConfig {
+                backend,
+                device,
+                format,
+                generation: 0,
+            }),
+            errors: broadcast::channel(8).0,
+        }
+    }
+
+    /// Switches the output, the sink is rebuilt before the next packet is written.
+    pub fn switch(&self, backend: Option<String>, device: Option<String>) -> anyhow::Result<()> {
+        if let Some(name) = backend.as_deref()
+            && audio_backend::find(Some(name.to_string())).is_none()
+        {
+            anyhow::bail!("Unknown audio backend {}", name);
+        }
+        let mut config = self.config.lock().unwrap();
+        config.backend = backend;
+        config.device = device;
+        config.generation += 1;
+        Ok(())
+    }
+
+    /// Errors from opening or writing to the output device, meant for the UI.
+    pub fn errors(&self) -> broadcast::Receiver<String> {
+        self.errors.subscribe()
+    }
+
+    fn report(&self, error: String) {
+        log::error!("{}", error);
+        let _ = self.errors.send(error);
+    }
+
+    /// Opens the configured device, falling back to the backend's default device and finally
+    /// to a silent sink so the player thread keeps running.
+    fn open(&self) -> (Box<dyn Sink>, u64) {
+        let config = self.config.lock().unwrap().clone();
+        match open_sink(&config) {
+            Ok(sink) => return (sink, config.generation),
+            Err(e) => self.report(e),
+        }
+        if config.device.is_some() {
+            let fallback = OutputConfig {
+                device: None,
+                ..config.clone()
+            };
+            match open_sink(&fallback) {
+                Ok(sink) => return (sink, config.generation),
+                Err(e) => self.report(e),
+            }
+        }
+        (Box::new(NullSink), config.generation)
+    }
+
+    /// Builds the sink handed to librespot's `Player`.
+    pub fn sink(self: &Arc<Self>) -> Box<dyn Sink> {
+        let (inner, generation) = self.open();
+        Box::new(SwitchableSink {
+            output: self.clone(),
+            inner,
+            generation,
+            running: false,
+        })
+    }
+}
+
+fn open_sink(config: &OutputConfig) -> Result<Box<dyn Sink>, String> {
+    let builder = audio_backend::find(config.backend.clone())
+        .ok_or_else(|| format!("Unknown audio backend {:?}", config.backend))?;
+    if let Some(device) = config.device.as_deref() {
+        let available = devices(config.backend.as_deref());
+        if !available.is_empty() && !available.iter().any(|d| d == device) {
+            return Err(format!("Audio device {} is not available", device));
+        }
+    }
+    let device = config.device.clone();
+    let format = config.format;
+    // Backends panic when the device cannot be opened.
+    std::panic::catch_unwind(AssertUnwindSafe(|| builder(device, format))).map_err(|_| {
+        format!(
+            "Failed to open audio device {}",
+            config.device.as_deref().unwrap_or("default")
+        )
+    })
+}
+
+struct SwitchableSink {
+    output: Arc<Output>,
+    inner: Box<dyn Sink>,
+    generation: u64,
+    running: bool,
+}
+
+impl SwitchableSink {
+    fn rebuild(&mut self) {
+        if self.running
+            && let Err(e) = self.inner.stop()
+        {
+            log::warn!("Failed to stop previous output: {}", e);
+        }
+        let (inner, generation) = self.output.open();
+        self.inner = inner;
+        self.generation = generation;
+        if self.running
+            && let Err(e) = self.inner.start()
+        {
+            self.output.report(e.to_string());
+        }
+    }
+
+    fn switch_if_needed(&mut self) {
+        if self.output.config.lock().unwrap().generation != self.generation {
+            self.rebuild();
+        }
+    }
+}
+
+impl Sink for SwitchableSink {
+    fn start(&mut self) -> SinkResult<()> {
+        self.switch_if_needed();
+        self.running = true;
+        self.inner.start()
+    }
+
+    fn stop(&mut self) -> SinkResult<()> {
+        self.running = false;
+        self.inner.stop()
+    }
+
+    fn write(&mut self, packet: AudioPacket, converter: &mut Converter) -> SinkResult<()> {
+        self.switch_if_needed();
+        if let Err(e) = self.inner.write(packet, converter) {
+            // Most likely the device went away, carry on with the default one.
+            self.output.report(e.to_string());
+            self.output.config.lock().unwrap().device = None;
+            self.rebuild();
+        }
+        Ok(())
+    }
+}
+
+/// Discards audio in real time, used when no output device can be opened.
+struct NullSink;
+
+impl Sink for NullSink {
+    fn write(&mut self, packet: AudioPacket, _: &mut Converter) -> SinkResult<()> {
+        let samples = packet.samples().map(|s| s.len()).unwrap_or_default();
+        std::thread::sleep(Duration::from_secs_f64(
+            samples as f64 / SAMPLES_PER_SECOND as f64,
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use librespot_metadata::audio::{AudioFileFormat, AudioFiles};
+use librespot_playback::config::Bitrate;
+
+/// The order in which librespot's player picks a file for the configured bitrate.
+fn format_priority(bitrate: Bitrate) -> [AudioFileFormat; 7] {
+    match bitrate {
+        Bitrate::Bitrate96 => [
+            AudioFileFormat::OGG_VORBIS_96,
+            AudioFileFormat::MP3_96,
+            AudioFileFormat::OGG_VORBIS_160,
+            AudioFileFormat::MP3_160,
+            AudioFileFormat::MP3_256,
+            AudioFileFormat::OGG_VORBIS_320,
+            AudioFileFormat::MP3_320,
+        ],
+        Bitrate::Bitrate160 => [
+            AudioFileFormat::OGG_VORBIS_160,
+            AudioFileFormat::MP3_160,
+            AudioFileFormat::OGG_VORBIS_96,
+            AudioFileFormat::MP3_96,
+            AudioFileFormat::MP3_256,
+            AudioFileFormat::OGG_VORBIS_320,
+            AudioFileFormat::MP3_320,
+        ],
+        Bitrate::Bitrate320 => [
+            AudioFileFormat::OGG_VORBIS_320,
+            AudioFileFormat::MP3_320,
+            AudioFileFormat::MP3_256,
+            AudioFileFormat::OGG_VORBIS_160,
+            AudioFileFormat::MP3_160,
+            AudioFileFormat::OGG_VORBIS_96,
+            AudioFileFormat::MP3_96,
+        ],
+    }
+}
+
+/// The file format that will actually be streamed for an item with the given files.
+pub fn streaming_format(bitrate: Bitrate, files: &AudioFiles) -> Option<AudioFileFormat> {
+    format_priority(bitrate)
+        .into_iter()
+        .find(|format| files.contains_key(format))
+}
+
+pub fn describe(format: AudioFileFormat) -> String {
+    let (codec, kbps) = match format {
+        AudioFileFormat::OGG_VORBIS_96 => ("Ogg Vorbis", 96),
+        AudioFileFormat::OGG_VORBIS_160 => ("Ogg Vorbis", 160),
+        AudioFileFormat::OGG_VORBIS_320 => ("Ogg Vorbis", 320),
+        AudioFileFormat::MP3_96 => ("MP3", 96),
+        AudioFileFormat::MP3_160 => ("MP3", 160),
+        AudioFileFormat::MP3_256 => ("MP3", 256),
+        AudioFileFormat::MP3_320 => ("MP3", 320),
+        other => return format!("{:?}", other),
+    };
+    format!("{} {} kbps", codec, kbps)
+}
+
+/// Whether the device is currently running off its battery.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn on_battery() -> bool {
+    let Ok(supplies) = std::fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    supplies.flatten().any(|supply| {
+        let read = |name: &str| {
+            std::fs::read_to_string(supply.path().join(name))
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+        read("type") == "Battery" && read("status") == "Discharging"
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub fn on_battery() -> bool {
+    false
+}
@@ -0,0 +1,368 @@
+use librespot_core::SpotifyId;
+use rand::seq::SliceRandom;
+
+/// Going back within this many milliseconds of the start of a track skips to the previous
+/// item, anything later restarts the current one.
+pub const RESTART_THRESHOLD_MS: u32 = 3000;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    Context,
+    Track,
+}
+
+impl RepeatMode {
+    /// The mode the repeat button switches to next.
+    pub fn cycle(self) -> RepeatMode {
+        match self {
+            RepeatMode::Off => RepeatMode::Context,
+            RepeatMode::Context => RepeatMode::Track,
+            RepeatMode::Track => RepeatMode::Off,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Queue {
+    /// Tracks in context order.
+    tracks: Vec<SpotifyId>,
+    /// Play order as indices into `tracks`, a permutation of them while shuffling.
+    order: Vec<usize>,
+    /// Index into `order`.
+    cursor: Option<usize>,
+    position_ms: u32,
+    context_uri: Option<String>,
+    shuffle: bool,
+    repeat: RepeatMode,
+}
+
+impl Queue {
+    /// Replaces the queue contents and moves the cursor to `start`.
+    pub fn set(
+        &mut self,
+        tracks: Vec<SpotifyId>,
+        start: usize,
+        context_uri: Option<String>,
+    ) -> Option<SpotifyId> {
+        self.order = (0..tracks.len()).collect();
+        self.cursor = (start < tracks.len()).then_some(start);
+        self.tracks = tracks;
+        self.position_ms = 0;
+        self.context_uri = context_uri;
+        if self.shuffle {
+            self.shuffle_order();
+        }
+        self.current()
+    }
+
+    /// The playlist, album or collection the queue was started from, if any.
+    pub fn context_uri(&self) -> Option<&str> {
+        self.context_uri.as_deref()
+    }
+
+    pub fn push(&mut self, track: SpotifyId) {
+        self.order.push(self.tracks.len());
+        self.tracks.push(track);
+    }
+
+    /// Tracks still to play after the current one, in play order.
+    pub fn upcoming(&self) -> Vec<SpotifyId> {
+        let start = self.cursor.map_or(0, |i| i + 1).min(self.order.len());
+        self.order[start..]
+            .iter()
+            .map(|&i| self.tracks[i])
+            .collect()
+    }
+
+    /// Drops the `index`th track of `upcoming`.
+    pub fn remove_upcoming(&mut self, index: usize) -> Option<SpotifyId> {
+        let at = self.cursor.map_or(0, |i| i + 1) + index;
+        if at >= self.order.len() {
+            return None;
+        }
+        let removed = self.order.remove(at);
+        for i in &mut self.order {
+            if *i > removed {
+                *i -= 1;
+            }
+        }
+        Some(self.tracks.remove(removed))
+    }
+
+    pub fn current(&self) -> Option<SpotifyId> {
+        self.cursor.map(|i| self.tracks[self.order[i]])
+    }
+
+    /// The track that will play once the current one ends.
+    pub fn peek_next(&self) -> Option<SpotifyId> {
+        if self.repeat == RepeatMode::Track {
+            return self.current();
+        }
+        self.next_index().map(|i| self.tracks[self.order[i]])
+    }
+
+    /// Moves to the track that plays once the current one ends, honouring repeat-one.
+    pub fn advance(&mut self) -> Option<SpotifyId> {
+        if self.repeat == RepeatMode::Track && self.cursor.is_some() {
+            self.position_ms = 0;
+            return self.current();
+        }
+        self.skip()
+    }
+
+    /// Moves the cursor forward, returning the new current track.
+    /// The cursor is left untouched at the end of the queue unless repeating the context.
+    pub fn skip(&mut self) -> Option<SpotifyId> {
+        let next = self.next_index()?;
+        self.cursor = Some(next);
+        self.position_ms = 0;
+        self.current()
+    }
+
+    /// Moves the cursor back, returning the new current track.
+    /// The cursor is left untouched at the start of the queue unless repeating the context.
+    pub fn back(&mut self) -> Option<SpotifyId> {
+        let cursor = self.cursor?;
+        let prev = match cursor.checked_sub(1) {
+            Some(prev) => prev,
+            None if self.repeat == RepeatMode::Context => self.order.len() - 1,
+            None => return None,
+        };
+        self.cursor = Some(prev);
+        self.position_ms = 0;
+        self.current()
+    }
+
+    /// Goes back a track, unless the current one played past `RESTART_THRESHOLD_MS`. Returns
+    /// the track to load, `None` when the current one should restart instead.
+    pub fn previous(&mut self) -> Option<SpotifyId> {
+        if self.position_ms > RESTART_THRESHOLD_MS {
+            return None;
+        }
+        self.back()
+    }
+
+    pub fn position_ms(&self) -> u32 {
+        self.position_ms
+    }
+
+    pub fn set_position_ms(&mut self, position_ms: u32) {
+        self.position_ms = position_ms;
+    }
+
+    pub fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+
+    /// Shuffling keeps the current track playing and randomises the rest of the context once,
+    /// un-shuffling restores context order from the current track.
+    pub fn set_shuffle(&mut self, shuffle: bool) {
+        if self.shuffle == shuffle {
+            return;
+        }
+        self.shuffle = shuffle;
+        if shuffle {
+            self.shuffle_order();
+        } else {
+            let current = self.cursor.map(|i| self.order[i]);
+            self.order = (0..self.tracks.len()).collect();
+            self.cursor = current;
+        }
+    }
+
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn set_repeat(&mut self, repeat: RepeatMode) {
+        self.repeat = repeat;
+    }
+
+    fn next_index(&self) -> Option<usize> {
+        let next = self.cursor.map_or(0, |i| i + 1);
+        if next < self.order.len() {
+            Some(next)
+        } else if self.repeat == RepeatMode::Context && !self.order.is_empty() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    fn shuffle_order(&mut self) {
+        let mut rest: Vec<usize> = (0..self.tracks.len()).collect();
+        let current = self.cursor.map(|i| rest.remove(self.order[i]));
+        rest.shuffle(&mut rand::rng());
+        self.order = current.into_iter().chain(rest).collect();
+        self.cursor = current.map(|_| 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracks(count: usize) -> Vec<SpotifyId> {
+        (1..=count)
+            .map(|i| SpotifyId::from_uri(&format!("spotify:track:{:0>22}", i)).unwrap())
+            .collect()
+    }
+
+    fn queue(count: usize, start: usize) -> (Queue, Vec<SpotifyId>) {
+        let ids = tracks(count);
+        let mut queue = Queue::default();
+        queue.set(ids.clone(), start, None);
+        (queue, ids)
+    }
+
+    #[test]
+    fn set_starts_at_the_given_track() {
+        let (queue, ids) = queue(3, 1);
+        assert_eq!(queue.current(), Some(ids[1]));
+        assert_eq!(queue.upcoming(), vec![ids[2]]);
+    }
+
+    #[test]
+    fn set_out_of_range_has_no_current_track() {
+        let (queue, ids) = queue(3, 3);
+        assert_eq!(queue.current(), None);
+        assert_eq!(queue.upcoming(), ids);
+    }
+
+    #[test]
+    fn skip_and_back_move_the_cursor() {
+        let (mut queue, ids) = queue(3, 0);
+        assert_eq!(queue.skip(), Some(ids[1]));
+        assert_eq!(queue.skip(), Some(ids[2]));
+        assert_eq!(queue.back(), Some(ids[1]));
+        assert_eq!(queue.current(), Some(ids[1]));
+    }
+
+    #[test]
+    fn skip_stops_at_the_end_of_the_queue() {
+        let (mut queue, ids) = queue(2, 1);
+        assert_eq!(queue.skip(), None);
+        assert_eq!(queue.advance(), None);
+        assert_eq!(queue.peek_next(), None);
+        assert_eq!(queue.current(), Some(ids[1]));
+    }
+
+    #[test]
+    fn back_stops_at_the_start_of_the_queue() {
+        let (mut queue, ids) = queue(2, 0);
+        assert_eq!(queue.back(), None);
+        assert_eq!(queue.current(), Some(ids[0]));
+    }
+
+    #[test]
+    fn moving_resets_the_position() {
+        let (mut queue, _) = queue(2, 0);
+        queue.set_position_ms(10_000);
+        queue.skip();
+        assert_eq!(queue.position_ms(), 0);
+    }
+
+    #[test]
+    fn previous_goes_back_near_the_start_of_a_track() {
+        let (mut queue, ids) = queue(3, 1);
+        queue.set_position_ms(RESTART_THRESHOLD_MS);
+        assert_eq!(queue.previous(), Some(ids[0]));
+        assert_eq!(queue.current(), Some(ids[0]));
+    }
+
+    #[test]
+    fn previous_restarts_past_the_threshold() {
+        let (mut queue, ids) = queue(3, 1);
+        queue.set_position_ms(RESTART_THRESHOLD_MS + 1);
+        assert_eq!(queue.previous(), None);
+        assert_eq!(queue.current(), Some(ids[1]));
+    }
+
+    #[test]
+    fn push_appends_to_upcoming() {
+        let (mut queue, ids) = queue(2, 0);
+        let extra = tracks(3)[2];
+        queue.push(extra);
+        assert_eq!(queue.upcoming(), vec![ids[1], extra]);
+    }
+
+    #[test]
+    fn remove_upcoming_keeps_the_current_track() {
+        let (mut queue, ids) = queue(4, 1);
+        assert_eq!(queue.remove_upcoming(0), Some(ids[2]));
+        assert_eq!(queue.remove_upcoming(5), None);
+        assert_eq!(queue.current(), Some(ids[1]));
+        assert_eq!(queue.upcoming(), vec![ids[3]]);
+        assert_eq!(queue.skip(), Some(ids[3]));
+    }
+
+    #[test]
+    fn shuffle_keeps_the_current_track_first() {
+        let (mut queue, ids) = queue(20, 7);
+        queue.set_shuffle(true);
+        assert!(queue.shuffle());
+        assert_eq!(queue.current(), Some(ids[7]));
+        assert_eq!(queue.back(), None);
+        let mut played: Vec<SpotifyId> = queue.upcoming();
+        played.push(ids[7]);
+        played.sort_by_key(|id| id.to_base62().unwrap());
+        assert_eq!(played, ids);
+    }
+
+    #[test]
+    fn unshuffle_restores_context_order_from_the_current_track() {
+        let (mut queue, ids) = queue(20, 7);
+        queue.set_shuffle(true);
+        let current = queue.skip().unwrap();
+        queue.set_shuffle(false);
+        assert_eq!(queue.current(), Some(current));
+        let at = ids.iter().position(|&id| id == current).unwrap();
+        assert_eq!(queue.upcoming(), ids[at + 1..]);
+    }
+
+    #[test]
+    fn set_while_shuffling_shuffles_the_new_context() {
+        let ids = tracks(20);
+        let mut queue = Queue::default();
+        queue.set_shuffle(true);
+        queue.set(ids.clone(), 4, None);
+        assert_eq!(queue.current(), Some(ids[4]));
+        assert_eq!(queue.upcoming().len(), 19);
+    }
+
+    #[test]
+    fn repeat_cycles_off_context_track() {
+        assert_eq!(RepeatMode::Off.cycle(), RepeatMode::Context);
+        assert_eq!(RepeatMode::Context.cycle(), RepeatMode::Track);
+        assert_eq!(RepeatMode::Track.cycle(), RepeatMode::Off);
+    }
+
+    #[test]
+    fn repeat_context_wraps_around() {
+        let (mut queue, ids) = queue(3, 2);
+        queue.set_repeat(RepeatMode::Context);
+        assert_eq!(queue.peek_next(), Some(ids[0]));
+        assert_eq!(queue.advance(), Some(ids[0]));
+        assert_eq!(queue.back(), Some(ids[2]));
+    }
+
+    #[test]
+    fn repeat_track_replays_on_advance_but_not_on_skip() {
+        let (mut queue, ids) = queue(3, 0);
+        queue.set_repeat(RepeatMode::Track);
+        queue.set_position_ms(5_000);
+        assert_eq!(queue.peek_next(), Some(ids[0]));
+        assert_eq!(queue.advance(), Some(ids[0]));
+        assert_eq!(queue.position_ms(), 0);
+        assert_eq!(queue.skip(), Some(ids[1]));
+    }
+
+    #[test]
+    fn repeat_off_ends_the_queue() {
+        let (mut queue, _) = queue(3, 2);
+        queue.set_repeat(RepeatMode::Off);
+        assert_eq!(queue.advance(), None);
+    }
+}
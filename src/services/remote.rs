@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use rspotify::model::{CurrentPlaybackContext, RepeatState};
+
+use super::queue::RepeatMode;
+
+/// How often the playback state of a remote device is fetched while controlling it.
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A Spotify Connect device controlled through the Web API.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteDevice {
+    pub id: String,
+    pub name: String,
+    pub shuffle: bool,
+    pub repeat: RepeatMode,
+}
+
+impl RemoteDevice {
+    pub fn new(id: String) -> RemoteDevice {
+        RemoteDevice {
+            id,
+            ..Default::default()
+        }
+    }
+
+    /// Refreshes the device from a playback state fetched from the Web API.
+    pub fn update(&mut self, playback: &CurrentPlaybackContext) {
+        if let Some(id) = &playback.device.id {
+            self.id = id.clone();
+        }
+        self.name = playback.device.name.clone();
+        self.shuffle = playback.shuffle_state;
+        self.repeat = repeat_mode(playback.repeat_state);
+    }
+}
+
+/// A player control forwarded to a remote device.
+#[derive(Debug, Clone, Copy)]
+pub enum RemoteCommand {
+    Play,
+    Pause,
+    Next,
+    Previous,
+    Seek(u32),
+    Shuffle(bool),
+    Repeat(RepeatMode),
+    /// Volume in percent.
+    Volume(u8),
+}
+
+pub fn repeat_mode(state: RepeatState) -> RepeatMode {
+    match state {
+        RepeatState::Off => RepeatMode::Off,
+        RepeatState::Context => RepeatMode::Context,
+        RepeatState::Track => RepeatMode::Track,
+    }
+}
+
+pub fn repeat_state(mode: RepeatMode) -> RepeatState {
+    match mode {
+        RepeatMode::Off => RepeatState::Off,
+        RepeatMode::Context => RepeatState::Context,
+        RepeatMode::Track => RepeatState::Track,
+    }
+}
@@ -0,0 +1,231 @@
+use std::time::Duration;
+
+use rspotify::{ClientError, http::HttpError};
+use tokio_util::sync::CancellationToken;
+
+use super::error::Error;
+
+/// How often and how long to keep retrying a failed Web API request.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included.
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for every one after it.
+    pub base_delay: Duration,
+    /// Longest wait between attempts. A `Retry-After` beyond it is not waited out.
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after failed attempt number `attempt`, counted from 1, or `None` to
+    /// give up. `transient` tells whether `error` came from a 5xx answer or a broken connection.
+    pub fn delay(&self, attempt: u32, error: &Error, transient: bool) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match error {
+            Error::RateLimited {
+                retry_after: Some(secs),
+            } => {
+                let delay = Duration::from_secs(*secs).max(self.base_delay);
+                (delay <= self.max_delay).then_some(delay)
+            }
+            Error::RateLimited { retry_after: None } => Some(self.backoff(attempt)),
+            _ if transient => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    /// Exponential backoff with jitter, somewhere between half and all of the doubled delay so
+    /// that clients failing together do not retry together.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+        let half = cap / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// Whether a request failed on the way rather than being refused, so it may work when retried.
+pub fn is_transient(error: &ClientError) -> bool {
+    match error {
+        ClientError::Http(http) => match http.as_ref() {
+            HttpError::StatusCode(response) => {
+                let status = response.status().as_u16();
+                status >= 500 || status == 408
+            }
+            HttpError::Client(_) => true,
+        },
+        ClientError::Io(_) => true,
+        _ => false,
+    }
+}
+
+/// Sleeps for `delay`, failing with `Error::Cancelled` when `shutdown` fires first.
+pub async fn wait(delay: Duration, shutdown: &CancellationToken) -> Result<(), Error> {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => Ok(()),
+        _ = shutdown.cancelled() => Err(Error::Cancelled),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    fn rate_limited(retry_after: Option<u64>) -> Error {
+        Error::RateLimited { retry_after }
+    }
+
+    fn status_error(status: u16) -> ClientError {
+        let response = hyper::Response::builder()
+            .status(status)
+            .body(Vec::<u8>::new())
+            .unwrap();
+        ClientError::Http(Box::new(HttpError::StatusCode(response.into())))
+    }
+
+    #[test]
+    fn retry_after_takes_precedence_over_backoff() {
+        let policy = policy();
+        for (retry_after, expected) in [
+            (Some(2), Some(Duration::from_secs(2))),
+            (Some(30), Some(Duration::from_secs(30))),
+            // Shorter than the base delay is raised to it.
+            (Some(0), Some(Duration::from_millis(500))),
+            // Longer than the longest wait gives up instead.
+            (Some(31), None),
+            (Some(3600), None),
+        ] {
+            assert_eq!(
+                policy.delay(1, &rate_limited(retry_after), false),
+                expected,
+                "Retry-After {:?}",
+                retry_after
+            );
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_within_jitter_bounds() {
+        let policy = policy();
+        for attempt in 1..=3 {
+            let cap = Duration::from_millis(500) * 2u32.pow(attempt - 1);
+            for _ in 0..100 {
+                for (error, transient) in [
+                    (rate_limited(None), false),
+                    (Error::Network("HTTP 503".into()), true),
+                ] {
+                    let delay = policy.delay(attempt, &error, transient).unwrap();
+                    assert!(
+                        delay >= cap / 2 && delay <= cap,
+                        "attempt {}: {:?} outside {:?}..={:?}",
+                        attempt,
+                        delay,
+                        cap / 2,
+                        cap
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            ..policy()
+        };
+        for attempt in [7, 20, 40, 99] {
+            let delay = policy.delay(attempt, &rate_limited(None), false).unwrap();
+            assert!(
+                delay >= policy.max_delay / 2,
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+            assert!(
+                delay <= policy.max_delay,
+                "attempt {}: {:?}",
+                attempt,
+                delay
+            );
+        }
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let policy = policy();
+        let network = Error::Network("HTTP 502".into());
+        assert!(policy.delay(3, &network, true).is_some());
+        assert_eq!(policy.delay(4, &network, true), None);
+        assert_eq!(policy.delay(4, &rate_limited(Some(1)), false), None);
+        assert_eq!(policy.delay(4, &rate_limited(None), false), None);
+    }
+
+    #[test]
+    fn refusals_are_not_retried() {
+        let policy = policy();
+        for error in [
+            Error::Auth("Invalid access token".into()),
+            Error::NotFound("Invalid playlist Id".into()),
+            Error::PremiumRequired,
+            Error::Network("Bad request".into()),
+        ] {
+            assert_eq!(policy.delay(1, &error, false), None, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn transient_errors() {
+        for (status, transient) in [
+            (500, true),
+            (502, true),
+            (503, true),
+            (408, true),
+            (400, false),
+            (401, false),
+            (404, false),
+            (429, false),
+        ] {
+            assert_eq!(
+                is_transient(&status_error(status)),
+                transient,
+                "HTTP {}",
+                status
+            );
+        }
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        assert!(is_transient(&ClientError::Io(io)));
+        assert!(!is_transient(&ClientError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn wait_is_cut_short_by_shutdown() {
+        let shutdown = CancellationToken::new();
+        shutdown.cancel();
+        let waited = wait(Duration::from_secs(60), &shutdown).await;
+        assert!(matches!(waited, Err(Error::Cancelled)));
+        assert!(
+            wait(Duration::ZERO, &CancellationToken::new())
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,479 @@
+use std::{path::PathBuf, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use librespot_core::{SessionConfig, config::DeviceType};
+use librespot_playback::{
+    audio_backend,
+    config::{
+        AudioFormat, Bitrate, NormalisationMethod, NormalisationType, PlayerConfig, VolumeCtrl,
+    },
+    player::duration_to_coefficient,
+};
+use robius_directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use super::quality;
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+pub fn project_dirs() -> anyhow::Result<ProjectDirs> {
+    ProjectDirs::from("com", "meghdip", "taan").context("Failed to get project directories")
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub audio: AudioSettings,
+    pub normalisation: NormalisationSettings,
+    pub cache: CacheSettings,
+    pub connect: ConnectSettings,
+    pub network: NetworkSettings,
+    pub ui: UiSettings,
+    pub lyrics: LyricsSettings,
+    pub api: ApiSettings,
+    /// Why the settings file could not be loaded, in which case these are the defaults and
+    /// saving them would overwrite the user's file.
+    #[serde(skip)]
+    pub load_error: Option<String>,
+}
+
+/// When to stream at `audio.metered_bitrate` instead of `audio.bitrate`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataSaver {
+    /// Only when Taan starts on battery. The player keeps its bitrate for the whole session,
+    /// so plugging in or unplugging the charger applies from the next start. Metered networks
+    /// are not detected.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// Streaming bitrate in kbps: 96, 160 or 320.
+    pub bitrate: u32,
+    /// Bitrate used when the data saver is on.
+    pub metered_bitrate: u32,
+    pub data_saver: DataSaver,
+    /// librespot audio backend name, the first compiled backend when unset.
+    pub backend: Option<String>,
+    /// Backend specific output device, the system default when unset.
+    pub device: Option<String>,
+    /// Sample format handed to the backend, e.g. "S16" or "F32".
+    pub format: String,
+    /// Volume curve: "linear", "log" or "cubic".
+    pub volume_curve: String,
+    pub gapless: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            bitrate: 160,
+            metered_bitrate: 96,
+            data_saver: DataSaver::Auto,
+            backend: None,
+            device: None,
+            format: "S16".into(),
+            volume_curve: "log".into(),
+            gapless: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalisationSettings {
+    pub enabled: bool,
+    /// "track", "album" or "auto", which uses album gain while playing an album.
+    pub gain_type: String,
+    /// "basic" or "dynamic", the latter runs a limiter above the threshold.
+    pub method: String,
+    pub pregain_db: f64,
+    pub threshold_dbfs: f64,
+    pub attack_ms: u64,
+    pub release_ms: u64,
+    pub knee_db: f64,
+}
+
+impl Default for NormalisationSettings {
+    fn default() -> Self {
+        NormalisationSettings {
+            enabled: false,
+            gain_type: "auto".into(),
+            method: "dynamic".into(),
+            pregain_db: 0.0,
+            threshold_dbfs: -2.0,
+            attack_ms: 5,
+            release_ms: 100,
+            knee_db: 5.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheSettings {
+    pub audio_cache: bool,
+    /// Upper bound for the audio cache in megabytes, unbounded when unset.
+    pub audio_cache_size_mb: Option<u64>,
+}
+
+impl Default for CacheSettings {
+    fn default() -> Self {
+        CacheSettings {
+            audio_cache: true,
+            audio_cache_size_mb: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectSettings {
+    /// Show up as a Spotify Connect device other clients can play to.
+    pub enabled: bool,
+    pub name: String,
+    /// Icon shown in other clients, e.g. "computer", "speaker" or "tv".
+    pub device_type: String,
+}
+
+impl Default for ConnectSettings {
+    fn default() -> Self {
+        ConnectSettings {
+            enabled: true,
+            name: "Taan".into(),
+            device_type: "computer".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// HTTP proxy for the librespot session, e.g. "http://127.0.0.1:8080".
+    pub proxy: Option<String>,
+    /// Base URL of the Web API, e.g. a local mock server. Spotify's when unset.
+    pub web_api_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    pub show_album_column: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        UiSettings {
+            show_album_column: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsSettings {
+    /// Folder searched for .lrc files when Spotify has no lyrics for a track, the "lyrics"
+    /// folder in the data directory when unset.
+    pub local_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    /// Serve the local JSON control API.
+    pub enabled: bool,
+    /// A loopback address such as "127.0.0.1:8899", or the path of a Unix socket.
+    pub listen: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        ApiSettings {
+            enabled: false,
+            listen: "127.0.0.1:8899".into(),
+        }
+    }
+}
+
+/// Where the control API listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiListen {
+    Tcp(std::net::SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Settings {
+    pub fn path() -> anyhow::Result<PathBuf> {
+        Ok(project_dirs()?.config_dir().join(SETTINGS_FILE))
+    }
+
+    /// Reads and validates the settings file, writing the defaults if there is none yet. A
+    /// failed first write is only logged, the defaults still apply and can be saved later.
+    pub fn load() -> anyhow::Result<Settings> {
+        let path = Settings::path()?;
+        if !path.exists() {
+            let settings = Settings::default();
+            if let Err(e) = settings.save() {
+                log::warn!("Failed to write the default settings: {:#}", e);
+            }
+            return Ok(settings);
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let settings: Settings =
+            toml::from_str(&text).with_context(|| format!("Failed to parse {}", path.display()))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// The defaults, standing in for a settings file that failed to load with `error`. They
+    /// are never saved, so the file stays as it is until the user fixes it.
+    pub fn fallback(error: &anyhow::Error) -> Settings {
+        Settings {
+            load_error: Some(format!("{:#}", error)),
+            ..Settings::default()
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        if let Some(error) = &self.load_error {
+            anyhow::bail!(
+                "Not saving over the settings file, fix it and restart Taan first: {}",
+                error
+            );
+        }
+        let path = Settings::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.bitrate()?;
+        self.metered_bitrate()?;
+        self.audio_format()?;
+        self.volume_ctrl()?;
+        self.audio_backend()?;
+        self.validate_normalisation()?;
+        self.connect_device_type()?;
+        self.session_config()?;
+        self.web_api_url()?;
+        self.api_listen()?;
+        match self.cache.audio_cache_size_mb {
+            Some(0) => anyhow::bail!("cache.audio_cache_size_mb must be greater than 0"),
+            Some(mb) if mb.checked_mul(1024 * 1024).is_none() => {
+                anyhow::bail!("cache.audio_cache_size_mb is too large")
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn bitrate(&self) -> anyhow::Result<Bitrate> {
+        parse_bitrate("audio.bitrate", self.audio.bitrate)
+    }
+
+    pub fn metered_bitrate(&self) -> anyhow::Result<Bitrate> {
+        parse_bitrate("audio.metered_bitrate", self.audio.metered_bitrate)
+    }
+
+    /// The bitrate to stream at, taking the data saver mode into account. Read once when the
+    /// player is set up.
+    pub fn streaming_bitrate(&self) -> anyhow::Result<Bitrate> {
+        let metered = match self.audio.data_saver {
+            DataSaver::Auto => quality::on_battery(),
+            DataSaver::Always => true,
+            DataSaver::Never => false,
+        };
+        if metered {
+            self.metered_bitrate()
+        } else {
+            self.bitrate()
+        }
+    }
+
+    pub fn audio_format(&self) -> anyhow::Result<AudioFormat> {
+        AudioFormat::from_str(&self.audio.format)
+            .map_err(|_| anyhow::anyhow!("Unknown audio.format {:?}", self.audio.format))
+    }
+
+    pub fn volume_ctrl(&self) -> anyhow::Result<VolumeCtrl> {
+        match self.audio.volume_curve.to_lowercase().as_str() {
+            "linear" => Ok(VolumeCtrl::Linear),
+            "log" => Ok(VolumeCtrl::Log(VolumeCtrl::DEFAULT_DB_RANGE)),
+            "cubic" => Ok(VolumeCtrl::Cubic(VolumeCtrl::DEFAULT_DB_RANGE)),
+            other => {
+                anyhow::bail!("audio.volume_curve must be linear, log or cubic, got {other:?}")
+            }
+        }
+    }
+
+    pub fn audio_backend(&self) -> anyhow::Result<audio_backend::SinkBuilder> {
+        audio_backend::find(self.audio.backend.clone()).with_context(|| {
+            format!(
+                "Unknown audio.backend {:?}, available: {}",
+                self.audio.backend.as_deref().unwrap_or_default(),
+                audio_backend::BACKENDS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+    }
+
+    fn validate_normalisation(&self) -> anyhow::Result<()> {
+        let n = &self.normalisation;
+        NormalisationType::from_str(&n.gain_type).map_err(|_| {
+            anyhow::anyhow!(
+                "normalisation.gain_type must be auto, track or album, got {:?}",
+                n.gain_type
+            )
+        })?;
+        NormalisationMethod::from_str(&n.method).map_err(|_| {
+            anyhow::anyhow!(
+                "normalisation.method must be basic or dynamic, got {:?}",
+                n.method
+            )
+        })?;
+        check_range("normalisation.pregain_db", n.pregain_db, -10.0, 10.0)?;
+        check_range("normalisation.threshold_dbfs", n.threshold_dbfs, -10.0, 0.0)?;
+        check_range("normalisation.attack_ms", n.attack_ms as f64, 1.0, 500.0)?;
+        check_range("normalisation.release_ms", n.release_ms as f64, 1.0, 1000.0)?;
+        check_range("normalisation.knee_db", n.knee_db, 0.0, 10.0)?;
+        Ok(())
+    }
+
+    pub fn player_config(&self) -> anyhow::Result<PlayerConfig> {
+        self.validate_normalisation()?;
+        let n = &self.normalisation;
+        Ok(PlayerConfig {
+            bitrate: self.streaming_bitrate()?,
+            gapless: self.audio.gapless,
+            normalisation: n.enabled,
+            normalisation_type: NormalisationType::from_str(&n.gain_type).unwrap_or_default(),
+            normalisation_method: NormalisationMethod::from_str(&n.method).unwrap_or_default(),
+            normalisation_pregain_db: n.pregain_db,
+            normalisation_threshold_dbfs: n.threshold_dbfs,
+            normalisation_attack_cf: duration_to_coefficient(Duration::from_millis(n.attack_ms)),
+            normalisation_release_cf: duration_to_coefficient(Duration::from_millis(n.release_ms)),
+            normalisation_knee_db: n.knee_db,
+            position_update_interval: Some(Duration::from_secs(1)),
+            ..Default::default()
+        })
+    }
+
+    pub fn connect_device_type(&self) -> anyhow::Result<DeviceType> {
+        DeviceType::from_str(&self.connect.device_type).map_err(|_| {
+            anyhow::anyhow!("Unknown connect.device_type {:?}", self.connect.device_type)
+        })
+    }
+
+    pub fn session_config(&self) -> anyhow::Result<SessionConfig> {
+        let proxy = self
+            .network
+            .proxy
+            .as_deref()
+            .filter(|p| !p.is_empty())
+            .map(reqwest::Url::parse)
+            .transpose()
+            .context("Invalid network.proxy")?;
+        Ok(SessionConfig {
+            proxy,
+            ..Default::default()
+        })
+    }
+
+    /// The Web API base URL, with the trailing slash rspotify joins endpoints onto.
+    pub fn web_api_url(&self) -> anyhow::Result<String> {
+        let Some(url) = &self.network.web_api_url else {
+            return Ok(rspotify::DEFAULT_API_BASE_URL.to_string());
+        };
+        let parsed = reqwest::Url::parse(url).context("Invalid network.web_api_url")?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            anyhow::bail!("network.web_api_url must be an http or https URL, got {url:?}");
+        }
+        let mut url = parsed.to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        Ok(url)
+    }
+
+    pub fn api_listen(&self) -> anyhow::Result<ApiListen> {
+        let listen = self.api.listen.trim();
+        if let Ok(addr) = listen.parse::<std::net::SocketAddr>() {
+            // Anyone who can reach the API controls playback.
+            if !addr.ip().is_loopback() {
+                anyhow::bail!("api.listen must be a loopback address, got {listen:?}");
+            }
+            return Ok(ApiListen::Tcp(addr));
+        }
+        if listen.starts_with('/') {
+            return Ok(ApiListen::Unix(PathBuf::from(listen)));
+        }
+        anyhow::bail!("api.listen must be an address or an absolute socket path, got {listen:?}")
+    }
+
+    pub fn lyrics_dir(&self) -> anyhow::Result<PathBuf> {
+        match &self.lyrics.local_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(project_dirs()?.data_dir().join("lyrics")),
+        }
+    }
+
+    /// Audio cache size limit in bytes.
+    pub fn audio_cache_size_limit(&self) -> Option<u64> {
+        self.cache
+            .audio_cache_size_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+    }
+}
+
+fn parse_bitrate(key: &str, kbps: u32) -> anyhow::Result<Bitrate> {
+    Bitrate::from_str(&kbps.to_string())
+        .map_err(|_| anyhow::anyhow!("{key} must be 96, 160 or 320, got {kbps}"))
+}
+
+fn check_range(key: &str, value: f64, min: f64, max: f64) -> anyhow::Result<()> {
+    if !(min..=max).contains(&value) {
+        anyhow::bail!("{key} must be between {min} and {max}, got {value}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_settings_are_not_saved() {
+        let settings = Settings::fallback(&anyhow::anyhow!("expected `=`"));
+        let error = settings.save().unwrap_err();
+        assert!(format!("{:#}", error).contains("expected `=`"));
+    }
+
+    #[test]
+    fn huge_audio_cache_size_is_rejected() {
+        let mut settings = Settings::default();
+        settings.cache.audio_cache_size_mb = Some(u64::MAX);
+        assert!(settings.validate().is_err());
+        assert_eq!(settings.audio_cache_size_limit(), Some(u64::MAX));
+    }
+
+    #[test]
+    fn audio_cache_size_is_in_megabytes() {
+        let mut settings = Settings::default();
+        settings.cache.audio_cache_size_mb = Some(2);
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.audio_cache_size_limit(), Some(2 * 1024 * 1024));
+    }
+}
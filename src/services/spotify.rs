@@ -1,20 +1,57 @@
 #![allow(dead_code)]
 
-use std::{sync::Arc, time::Duration};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use futures::future::BoxFuture;
+use futures::{Stream, StreamExt};
 use http_cache_reqwest::{CACacheManager, CacheMode, CacheOptions, HttpCache, HttpCacheOptions};
 use image::EncodableLayout;
+use librespot_connect::ConnectConfig;
 use librespot_core::{
-    Error, Session, SessionConfig, SpotifyId, authentication::Credentials, cache::Cache,
+    Session, SessionConfig, SpotifyId, authentication::Credentials, cache::Cache,
 };
+use librespot_metadata::audio::AudioFiles;
 use librespot_playback::{
-    audio_backend,
-    config::{AudioFormat, PlayerConfig},
-    mixer::NoOpVolume,
-    player::Player,
+    config::{Bitrate, PlayerConfig, VolumeCtrl},
+    mixer::{Mixer, MixerConfig, softmixer::SoftMixer},
+    player::{Player, PlayerEvent},
 };
-use rspotify::{AuthCodeSpotify, ClientError, http::HttpError, model::{PlaylistId, PlaylistItem, SimplifiedPlaylist}, prelude::{BaseClient, OAuthClient}, ClientResult};
 use rspotify::model::SavedTrack;
+use rspotify::{
+    AuthCodeSpotify, ClientError, ClientResult,
+    http::HttpError,
+    model::{
+        AdditionalType, AlbumId, AlbumType, ArtistId, CurrentPlaybackContext, Device, EpisodeId,
+        FullAlbum, FullArtist, FullEpisode, FullShow, FullTrack, Page, PlaylistId, PlaylistItem,
+        SearchMultipleResult, SearchType, Show, ShowId, SimplifiedAlbum, SimplifiedEpisode,
+        SimplifiedPlaylist, SimplifiedTrack,
+    },
+    prelude::{BaseClient, OAuthClient},
+};
+use serde::de::DeserializeOwned;
+use tokio_util::sync::CancellationToken;
+
+use super::backend::{self, Backend, Pages};
+use super::connect::Receiver;
+use super::error::{self, Error};
+use super::lyrics;
+use super::output::Output;
+use super::quality;
+use super::queue::{Queue, RepeatMode};
+use super::remote::{self, RemoteCommand, RemoteDevice};
+use super::retry::{self, RetryPolicy};
+use super::settings::{Settings, project_dirs};
+
+/// Largest page the Web API serves for most list endpoints.
+const PAGE_SIZE: u32 = 50;
+const PLAYLIST_PAGE_SIZE: u32 = 100;
+
+/// How long before it expires the Web API token is replaced.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+/// How often the session and the token are checked. Polling rather than sleeping until the
+/// token expires catches up soon after the computer wakes from sleep.
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(30);
 
 pub const SPOTIFY_CLIENT_ID: &str = "65b708073fc0480ea92a077233ca87bd";
 
@@ -44,38 +81,85 @@ static OAUTH_SCOPES: &[&str] = &[
     "user-top-read",
 ];
 
+/// Whether the connection to Spotify is up, as shown next to the library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionHealth {
+    Connected,
+    /// The session dropped and a new one is being set up.
+    Reconnecting,
+    /// Reconnecting or refreshing the token failed, it is tried again shortly.
+    Disconnected,
+}
+
 #[derive(Clone)]
 pub struct SpotifyService {
-    session: Session,
-    pub player: Arc<Player>,
+    /// Replaced together with `player` when the session drops, librespot sessions cannot be
+    /// reconnected.
+    session: Arc<RwLock<Session>>,
+    session_config: SessionConfig,
+    player: Arc<tokio::sync::watch::Sender<Arc<Player>>>,
+    player_config: PlayerConfig,
+    mixer: Arc<SoftMixer>,
+    /// Spotify Connect receiver, unset when disabled in the settings.
+    connect: Option<Arc<Receiver>>,
+    output: Arc<Output>,
+    bitrate: Bitrate,
     client: Arc<AuthCodeSpotify>,
+    queue: Arc<Mutex<Queue>>,
+    /// The Connect device controlled through the Web API instead of playing locally.
+    remote: Arc<Mutex<Option<RemoteDevice>>>,
+    retry: RetryPolicy,
+    /// Held while the Web API token is refreshed, so concurrent requests refresh it once.
+    web_refresh: Arc<tokio::sync::Mutex<()>>,
+    /// Cancelled when the app quits, cutting pending requests and retries short.
+    shutdown: CancellationToken,
+    /// Hands out Web API tokens in place of the session, for tests against a mock Web API.
+    web_tokens: Option<Arc<dyn Fn() -> rspotify::Token + Send + Sync>>,
+    health: Arc<tokio::sync::watch::Sender<SessionHealth>>,
 }
-impl Default for SpotifyService {
-    fn default() -> SpotifyService {
-        let path = robius_directories::ProjectDirs::from("com", "meghdip", "taan")
-            .expect("Failed to get project directories, fatal");
+impl SpotifyService {
+    pub fn new(settings: &Settings, shutdown: CancellationToken) -> anyhow::Result<SpotifyService> {
+        let path = project_dirs()?;
+        let audio_cache = settings
+            .cache
+            .audio_cache
+            .then(|| path.cache_dir().join("audio_cache"));
         let cache = Cache::new(
             Some(path.cache_dir()),
             Some(path.cache_dir()),
-            Some(&path.cache_dir().join("audio_cache")),
-            None,
-        )
-        .expect("Failed to initialise cache, fatal");
-        let session = Session::new(SessionConfig::default(), Some(cache));
-        let player = Player::new(
-            PlayerConfig {
-                position_update_interval: Some(Duration::from_secs(1)),
+            audio_cache.as_deref(),
+            settings.audio_cache_size_limit(),
+        )?;
+        let mixer = SoftMixer::open(MixerConfig {
+            volume_ctrl: settings.volume_ctrl()?,
+            ..Default::default()
+        })?;
+        let volume = cache.volume().unwrap_or(VolumeCtrl::MAX_VOLUME / 2);
+        mixer.set_volume(volume);
+        let session_config = settings.session_config()?;
+        let session = Session::new(session_config.clone(), Some(cache));
+        settings.audio_backend()?;
+        let output = Arc::new(Output::new(
+            settings.audio.backend.clone(),
+            settings.audio.device.clone(),
+            settings.audio_format()?,
+        ));
+        let player_config = settings.player_config()?;
+        let bitrate = player_config.bitrate;
+        log::info!("Streaming at {:?}", bitrate);
+        let connect = if settings.connect.enabled {
+            let player = new_player(&player_config, session.clone(), &mixer, &output);
+            let config = ConnectConfig {
+                name: settings.connect.name.clone(),
+                device_type: settings.connect_device_type()?,
+                initial_volume: volume,
                 ..Default::default()
-            },
-            session.clone(),
-            Box::new(NoOpVolume),
-            || {
-                audio_backend::find(None).expect("Failed to initialise audio backend, fatal")(
-                    None,
-                    AudioFormat::default(),
-                )
-            },
-        );
+            };
+            Some(Arc::new(Receiver::new(config, player)))
+        } else {
+            None
+        };
+        let player = new_player(&player_config, session.clone(), &mixer, &output);
         let mut client = AuthCodeSpotify::default().with_middleware_arc(Arc::new(
             http_cache_reqwest::Cache(HttpCache {
                 mode: CacheMode::Default,
@@ -90,131 +174,654 @@ impl Default for SpotifyService {
             }),
         ));
         client.config.token_refreshing = false;
-        SpotifyService {
-            session,
-            player,
+        client.config.api_base_url = settings.web_api_url()?;
+        Ok(SpotifyService {
+            session: Arc::new(RwLock::new(session)),
+            session_config,
+            player: Arc::new(tokio::sync::watch::Sender::new(player)),
+            player_config,
+            mixer: Arc::new(mixer),
+            connect,
+            output,
+            bitrate,
             client: Arc::new(client),
-        }
+            queue: Arc::new(Mutex::new(Queue::default())),
+            remote: Arc::new(Mutex::new(None)),
+            retry: RetryPolicy::default(),
+            web_refresh: Arc::new(tokio::sync::Mutex::new(())),
+            shutdown,
+            web_tokens: None,
+            health: Arc::new(tokio::sync::watch::Sender::new(SessionHealth::Connected)),
+        })
     }
-}
-impl SpotifyService {
-    pub async fn init(&self) -> anyhow::Result<()> {
+
+    fn session(&self) -> Session {
+        self.session.read().unwrap().clone()
+    }
+
+    fn player(&self) -> Arc<Player> {
+        self.player.borrow().clone()
+    }
+
+    pub async fn init(&self) -> error::Result<()> {
         let creds = self
-            .session
+            .session()
             .cache()
-            .ok_or(Error::unauthenticated("No cache in session"))?
-            .credentials()
-            .ok_or(Error::unauthenticated("No cache in session"))?;
-        self.session.connect(creds, true).await?;
-        self.web_auth().await?;
+            .and_then(|cache| cache.credentials())
+            .ok_or_else(|| Error::Auth("No saved login".into()))?;
+        self.connect(creds).await?;
         // self.load_track("spotify:track:30aPCMAtkH6Cf5ejzY4cE4".to_string())?;
         Ok(())
     }
-    pub async fn connect(&self, creds: Credentials) -> anyhow::Result<()> {
-        self.session.connect(creds, true).await?;
+    pub async fn connect(&self, creds: Credentials) -> error::Result<()> {
+        match &self.connect {
+            // Spirc connects the session itself once its listeners are registered.
+            Some(connect) => {
+                connect
+                    .start(self.session(), creds, self.mixer.clone())
+                    .await?
+            }
+            None => self.session().connect(creds, true).await?,
+        }
         self.web_auth().await?;
         Ok(())
     }
 
-    pub async fn web_auth(&self) -> anyhow::Result<()> {
-        let token = self.session.login5().auth_token().await?;
+    pub async fn web_auth(&self) -> error::Result<()> {
+        if let Some(web_tokens) = &self.web_tokens {
+            self.set_web_token(web_tokens()).await;
+            return Ok(());
+        }
+        let token = self.session().login5().auth_token().await?;
 
+        let expires_in = chrono::TimeDelta::from_std(token.expires_in)
+            .map_err(|e| Error::Auth(format!("Invalid token expiry: {}", e)))?;
         let rtoken = rspotify::Token {
             access_token: token.access_token,
-            expires_in: chrono::TimeDelta::from_std(token.expires_in)
-                .expect("Invalid expiry, fatal"),
+            expires_in,
+            expires_at: Some(chrono::Utc::now() + expires_in),
             scopes: token.scopes.into_iter().collect(),
             ..Default::default()
         };
 
-        *self.client.token.lock().await.unwrap() = Some(rtoken);
+        self.set_web_token(rtoken).await;
         Ok(())
     }
 
-    pub async fn get_me(&self) -> Result<rspotify::model::PrivateUser, Error> {
+    /// Gets a new Web API token unless another request already replaced `stale`, the token
+    /// that was turned down, while this one waited its turn.
+    async fn refresh_web_token(&self, stale: Option<String>) -> error::Result<()> {
+        let _refreshing = self.web_refresh.lock().await;
+        if self.web_access_token().await != stale {
+            return Ok(());
+        }
+        log::info!("Refreshing the Web API token");
+        self.web_auth().await
+    }
+
+    /// Whether the Web API token runs out within `TOKEN_REFRESH_MARGIN`. Tokens without an
+    /// expiry, such as those handed to `set_web_token`, are left alone.
+    async fn web_token_expires_soon(&self) -> bool {
+        let margin = chrono::TimeDelta::from_std(TOKEN_REFRESH_MARGIN).unwrap_or_default();
+        self.client
+            .token
+            .lock()
+            .await
+            .unwrap()
+            .as_ref()
+            .and_then(|token| token.expires_at)
+            .is_some_and(|expires_at| expires_at - chrono::Utc::now() < margin)
+    }
+
+    async fn web_access_token(&self) -> Option<String> {
         self.client
-            .current_user()
+            .token
+            .lock()
             .await
-            .map_err(Error::unauthenticated)
+            .unwrap()
+            .as_ref()
+            .map(|token| token.access_token.clone())
+    }
+
+    /// Authorizes Web API requests with `token`, as `web_auth` does with the session's.
+    pub async fn set_web_token(&self, token: rspotify::Token) {
+        *self.client.token.lock().await.unwrap() = Some(token);
+    }
+
+    pub async fn get_me(&self) -> error::Result<rspotify::model::PrivateUser> {
+        self.web_api(|| self.client.current_user()).await
+    }
+
+    /// The id other clients see this device's Spotify Connect receiver under.
+    pub fn device_id(&self) -> &str {
+        &self.session_config.device_id
     }
 
     pub fn is_connected(&self) -> bool {
-        self.session.username().is_empty()
+        self.session().username().is_empty()
     }
 
-    pub async fn get_user_playlists(
+    /// Walks a paginated Web API endpoint lazily, yielding one page of `page_size` items at a
+    /// time. `fetch` gets the limit and offset of the page to request.
+    pub fn paginate<'a, T, F, Fut>(
+        &'a self,
+        page_size: u32,
+        fetch: F,
+    ) -> impl Stream<Item = error::Result<Vec<T>>> + Send + 'a
+    where
+        T: DeserializeOwned + Send + 'a,
+        F: Fn(u32, u32) -> Fut + Clone + Send + Sync + 'a,
+        Fut: Future<Output = ClientResult<Page<T>>> + Send + 'a,
+    {
+        futures::stream::unfold(Some(0), move |offset| {
+            let fetch = fetch.clone();
+            async move {
+                let offset = offset?;
+                match self.web_api(|| fetch(page_size, offset)).await {
+                    Ok(page) => {
+                        // Skip by the page's limit, which counts items rspotify dropped as
+                        // null, but always past what arrived so a bogus limit cannot stall.
+                        let step = page.limit.max(page.items.len() as u32);
+                        let next =
+                            (page.next.is_some() && !page.items.is_empty()).then(|| offset + step);
+                        Some((Ok(page.items), next))
+                    }
+                    Err(e) => Some((Err(e), None)),
+                }
+            }
+        })
+    }
+
+    pub fn user_playlists(
         &self,
+    ) -> impl Stream<Item = error::Result<Vec<SimplifiedPlaylist>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client
+                .current_user_playlists_manual(Some(limit), Some(offset))
+        })
+    }
+
+    pub fn playlist_items(
+        &self,
+        id: PlaylistId<'static>,
+    ) -> impl Stream<Item = error::Result<Vec<PlaylistItem>>> + Send + '_ {
+        self.paginate(PLAYLIST_PAGE_SIZE, move |limit, offset| {
+            self.client
+                .playlist_items_manual(id.clone(), None, None, Some(limit), Some(offset))
+        })
+    }
+
+    pub fn saved_tracks(&self) -> impl Stream<Item = error::Result<Vec<SavedTrack>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client
+                .current_user_saved_tracks_manual(None, Some(limit), Some(offset))
+        })
+    }
+
+    pub async fn album(&self, id: AlbumId<'_>) -> error::Result<FullAlbum> {
+        self.web_api(|| self.client.album(id.clone(), None)).await
+    }
+
+    pub async fn is_album_saved(&self, id: AlbumId<'_>) -> error::Result<bool> {
+        let saved = self
+            .web_api(|| self.client.current_user_saved_albums_contains([id.clone()]))
+            .await?;
+        Ok(saved.first().copied().unwrap_or_default())
+    }
+
+    /// Adds album `id` to the user's library, or removes it when `saved` is false.
+    pub async fn set_album_saved(&self, id: AlbumId<'_>, saved: bool) -> error::Result<()> {
+        if saved {
+            self.web_api(|| self.client.current_user_saved_albums_add([id.clone()]))
+                .await
+        } else {
+            self.web_api(|| self.client.current_user_saved_albums_delete([id.clone()]))
+                .await
+        }
+    }
+
+    pub fn album_tracks(
+        &self,
+        id: AlbumId<'static>,
+    ) -> impl Stream<Item = error::Result<Vec<SimplifiedTrack>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client
+                .album_track_manual(id.clone(), None, Some(limit), Some(offset))
+        })
+    }
+
+    pub async fn artist(&self, id: ArtistId<'_>) -> error::Result<FullArtist> {
+        self.web_api(|| self.client.artist(id.clone())).await
+    }
+
+    pub async fn artist_top_tracks(&self, id: ArtistId<'_>) -> error::Result<Vec<FullTrack>> {
+        self.web_api(|| self.client.artist_top_tracks(id.clone(), None))
+            .await
+    }
+
+    /// Albums, singles and compilations of artist `id`, without the ones it only appears on.
+    pub fn artist_albums(
+        &self,
+        id: ArtistId<'static>,
+    ) -> impl Stream<Item = error::Result<Vec<SimplifiedAlbum>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client.artist_albums_manual(
+                id.clone(),
+                [AlbumType::Album, AlbumType::Single, AlbumType::Compilation],
+                None,
+                Some(limit),
+                Some(offset),
+            )
+        })
+    }
+
+    /// Spotify deprecated this endpoint and answers 404 for newer clients.
+    #[allow(deprecated)]
+    pub async fn related_artists(&self, id: ArtistId<'_>) -> error::Result<Vec<FullArtist>> {
+        self.web_api(|| self.client.artist_related_artists(id.clone()))
+            .await
+    }
+
+    pub async fn is_following_artist(&self, id: ArtistId<'_>) -> error::Result<bool> {
+        let following = self
+            .web_api(|| self.client.user_artist_check_follow([id.clone()]))
+            .await?;
+        Ok(following.first().copied().unwrap_or_default())
+    }
+
+    /// Follows artist `id`, or unfollows it when `follow` is false.
+    pub async fn set_following_artist(&self, id: ArtistId<'_>, follow: bool) -> error::Result<()> {
+        if follow {
+            self.web_api(|| self.client.user_follow_artists([id.clone()]))
+                .await
+        } else {
+            self.web_api(|| self.client.user_unfollow_artists([id.clone()]))
+                .await
+        }
+    }
+
+    pub fn saved_shows(&self) -> impl Stream<Item = error::Result<Vec<Show>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client.get_saved_show_manual(Some(limit), Some(offset))
+        })
+    }
+
+    pub async fn show(&self, id: ShowId<'_>) -> error::Result<FullShow> {
+        self.web_api(|| self.client.get_a_show(id.clone(), None))
+            .await
+    }
+
+    /// Episodes of show `id`, newest first.
+    pub fn show_episodes(
+        &self,
+        id: ShowId<'static>,
+    ) -> impl Stream<Item = error::Result<Vec<SimplifiedEpisode>>> + Send + '_ {
+        self.paginate(PAGE_SIZE, move |limit, offset| {
+            self.client
+                .get_shows_episodes_manual(id.clone(), None, Some(limit), Some(offset))
+        })
+    }
+
+    pub async fn episode(&self, id: EpisodeId<'_>) -> error::Result<FullEpisode> {
+        self.web_api(|| self.client.get_an_episode(id.clone(), None))
+            .await
+    }
+
+    /// Searches the catalogue for `query`, returning a page of `limit` results per type.
+    pub async fn search(
+        &self,
+        query: &str,
+        types: &[SearchType],
         limit: u32,
         offset: u32,
-    ) -> Result<Vec<SimplifiedPlaylist>, Error> {
+    ) -> error::Result<SearchMultipleResult> {
+        self.web_api(|| {
+            self.client.search_multiple(
+                query,
+                types.iter().copied(),
+                None,
+                None,
+                Some(limit),
+                Some(offset),
+            )
+        })
+        .await
+    }
+
+    /// Time-synced lyrics of a track, from the same source the official client uses.
+    pub async fn lyrics(&self, id: SpotifyId) -> error::Result<lyrics::Lyrics> {
+        let lyrics = librespot_metadata::Lyrics::get(&self.session(), &id).await?;
+        Ok(lyrics.into())
+    }
+
+    /// Starts playing the episodes `ids` from `start`, resuming it at `position_ms`.
+    pub fn play_episodes(
+        &self,
+        context_uri: Option<String>,
+        ids: &[String],
+        start: usize,
+        position_ms: u32,
+    ) -> anyhow::Result<()> {
+        let (episodes, start_at) = backend::parse_ids("episode", ids, start)?;
+        log::info!(
+            "Playing episodes {:?} from {} at {}",
+            context_uri,
+            start_at,
+            position_ms
+        );
+        self.play_queue_at(episodes, start_at, context_uri, position_ms);
+        Ok(())
+    }
+
+    fn play_queue_at(
+        &self,
+        tracks: Vec<SpotifyId>,
+        start: usize,
+        context_uri: Option<String>,
+        position_ms: u32,
+    ) {
+        if let Some(connect) = &self.connect {
+            connect.release();
+        }
+        // Playing locally ends remote control.
+        if self.remote_command(RemoteCommand::Pause) {
+            *self.remote.lock().unwrap() = None;
+        }
+        // Auto normalisation uses album gain only while an album is playing through.
+        let is_album = context_uri
+            .as_deref()
+            .is_some_and(|uri| uri.starts_with("spotify:album:"));
+        self.player().set_auto_normalise_as_album(is_album);
+        let current = self.queue.lock().unwrap().set(tracks, start, context_uri);
+        match current {
+            Some(track_id) => self.player().load(track_id, true, position_ms),
+            None => self.player().stop(),
+        }
+    }
+
+    /// Moves playback to another backend or device without interrupting the queue.
+    pub fn switch_output(
+        &self,
+        backend: Option<String>,
+        device: Option<String>,
+    ) -> anyhow::Result<()> {
+        log::info!("Switching output to {:?} {:?}", backend, device);
+        self.output.switch(backend, device)
+    }
+
+    pub async fn on_output_error<F>(&self, callback: F)
+    where
+        F: Fn(String),
+    {
+        let mut errors = self.output.errors();
         loop {
-            match self
-                .client
-                .current_user_playlists_manual(Some(limit), Some(offset))
-                .await
-            {
-                Ok(playlists) => {
-                    break Ok(playlists.items);
+            match errors.recv().await {
+                Ok(e) => callback(e),
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    }
+
+    /// The Spotify Connect handle while a remote client is driving playback.
+    fn remote(&self) -> Option<Arc<librespot_connect::Spirc>> {
+        self.connect.as_ref().and_then(|connect| connect.remote())
+    }
+
+    fn end_of_track(&self) {
+        let next = self.queue.lock().unwrap().advance();
+        match next {
+            Some(track_id) => self.player().load(track_id, true, 0),
+            None => log::info!("Reached the end of the queue"),
+        }
+    }
+
+    /// Keeps the queue in step with the player: advances on end of track, preloads the next
+    /// queued item and tracks the playback position for `previous`.
+    pub async fn drive_queue(&self) {
+        let events = follow_player(self.player.subscribe(), self.shutdown.clone());
+        let mut events = std::pin::pin!(events);
+        while let Some(event) = events.next().await {
+            match event {
+                PlayerEvent::EndOfTrack { track_id, .. }
+                    if self.queue.lock().unwrap().current() == Some(track_id) =>
+                {
+                    self.end_of_track();
                 }
-                Err(e) => {
-                    if self.requires_refresh(e).await {
-                        continue;
+                PlayerEvent::TimeToPreloadNextTrack { .. } => {
+                    if let Some(next) = self.queue.lock().unwrap().peek_next() {
+                        log::info!("Preloading next queued track: {}", next);
+                        self.player().preload(next);
                     }
-                    break Err(Error::unauthenticated("Failed to refresh client"));
                 }
+                PlayerEvent::Loading { position_ms, .. }
+                | PlayerEvent::Playing { position_ms, .. }
+                | PlayerEvent::Paused { position_ms, .. }
+                | PlayerEvent::PositionCorrection { position_ms, .. }
+                | PlayerEvent::PositionChanged { position_ms, .. }
+                | PlayerEvent::Seeked { position_ms, .. } => {
+                    self.queue.lock().unwrap().set_position_ms(position_ms);
+                }
+                _ => {}
             }
         }
     }
 
-    pub async fn get_playlist(
-        &self,
-        id: PlaylistId<'_>,
-        limit: u32,
-        offset: u32,
-    ) -> Result<Vec<PlaylistItem>, Error> {
+    pub fn remote_device(&self) -> Option<RemoteDevice> {
+        self.remote.lock().unwrap().clone()
+    }
+
+    /// Connect devices of the user that are currently online.
+    pub async fn get_devices(&self) -> error::Result<Vec<Device>> {
+        self.web_api(|| self.client.device()).await
+    }
+
+    /// Moves playback to `device_id`, or back to this device when it is empty or our own.
+    pub async fn transfer_playback(&self, device_id: String) -> error::Result<()> {
+        if device_id.is_empty() || device_id == self.device_id() {
+            let previous = self.remote.lock().unwrap().take();
+            if self.connect.is_some() {
+                self.web_api(|| self.client.transfer_playback(self.device_id(), Some(true)))
+                    .await?;
+            } else if let Some(previous) = previous {
+                self.web_api(|| self.client.pause_playback(Some(&previous.id)))
+                    .await?;
+            }
+            log::info!("Playing on this device");
+            return Ok(());
+        }
+        self.web_api(|| self.client.transfer_playback(&device_id, Some(true)))
+            .await?;
+        self.player().pause();
+        log::info!("Transferred playback to {}", device_id);
+        *self.remote.lock().unwrap() = Some(RemoteDevice::new(device_id));
+        Ok(())
+    }
+
+    /// Forwards `command` to the remote device if one is being controlled.
+    fn remote_command(&self, command: RemoteCommand) -> bool {
+        let Some(device) = self.remote_device() else {
+            return false;
+        };
+        let spotify = self.clone();
+        super::rt().spawn(async move {
+            let id = Some(device.id.as_str());
+            let client = &spotify.client;
+            let result = match command {
+                RemoteCommand::Play => spotify.web_api(|| client.resume_playback(id, None)).await,
+                RemoteCommand::Pause => spotify.web_api(|| client.pause_playback(id)).await,
+                RemoteCommand::Next => spotify.web_api(|| client.next_track(id)).await,
+                RemoteCommand::Previous => spotify.web_api(|| client.previous_track(id)).await,
+                RemoteCommand::Seek(position_ms) => {
+                    let position = chrono::TimeDelta::milliseconds(position_ms as i64);
+                    spotify.web_api(|| client.seek_track(position, id)).await
+                }
+                RemoteCommand::Shuffle(shuffle) => {
+                    spotify.web_api(|| client.shuffle(shuffle, id)).await
+                }
+                RemoteCommand::Repeat(repeat) => {
+                    let state = remote::repeat_state(repeat);
+                    spotify.web_api(|| client.repeat(state, id)).await
+                }
+                RemoteCommand::Volume(percent) => {
+                    spotify.web_api(|| client.volume(percent, id)).await
+                }
+            };
+            if let Err(e) = result {
+                log::error!("Failed to send {:?} to {}: {:#}", command, device.name, e);
+            }
+        });
+        true
+    }
+
+    /// Polls the playback state of the remote device while one is being controlled. `callback`
+    /// gets `None` once playback is back on this device.
+    pub async fn on_remote_playback<F>(&self, callback: F)
+    where
+        F: Fn(Option<CurrentPlaybackContext>),
+    {
+        let mut interval = tokio::time::interval(remote::POLL_INTERVAL);
+        let mut was_remote = false;
         loop {
-            match self
-                .client
-                .playlist_items_manual(id.clone(), None, None, Some(limit), Some(offset))
+            interval.tick().await;
+            if self.remote_device().is_none() {
+                if was_remote {
+                    callback(None);
+                }
+                was_remote = false;
+                continue;
+            }
+            let playback = match self
+                .web_api(|| {
+                    self.client
+                        .current_playback(None, None::<&[AdditionalType]>)
+                })
                 .await
             {
-                Ok(tracks) => {
-                    break Ok(tracks.items);
-                }
+                Ok(playback) => playback,
                 Err(e) => {
-                    if self.requires_refresh(e).await {
-                        continue;
-                    }
-                    break Err(Error::unauthenticated("Failed to refresh client"));
+                    log::error!("Failed to fetch remote playback: {:#}", e);
+                    continue;
+                }
+            };
+            // Playing on our own receiver or nowhere at all ends remote control.
+            let playback = playback.filter(|p| p.device.id.as_deref() != Some(self.device_id()));
+            {
+                let mut remote = self.remote.lock().unwrap();
+                match (&playback, remote.as_mut()) {
+                    (Some(playback), Some(device)) => device.update(playback),
+                    _ => *remote = None,
                 }
             }
+            was_remote = playback.is_some();
+            callback(playback);
         }
     }
 
-    pub async fn get_saved_tracks(&self) -> anyhow::Result<Vec<SavedTrack>> {
+    /// Follows the Spotify Connect session, pausing local playback when a remote client takes
+    /// over.
+    pub async fn drive_connect(&self) {
+        let Some(connect) = &self.connect else {
+            return;
+        };
+        let events = follow_player(connect.players(), self.shutdown.clone());
+        let mut events = std::pin::pin!(events);
+        while let Some(event) = events.next().await {
+            if connect.handle_event(&event) {
+                log::info!("Spotify Connect client took over playback");
+                self.player().stop();
+            }
+        }
+    }
+
+    /// Refreshes the Web API token before it expires and sets up a new session when the
+    /// current one drops, publishing the outcome as `SessionHealth`. Runs until shutdown.
+    pub async fn keep_session(&self) {
+        let mut checks = tokio::time::interval(SESSION_CHECK_INTERVAL);
+        checks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
         loop {
-            match self.client.current_user_saved_tracks_manual(None, Some(20), None).await {
-                Ok(tracks) => {
-                    break anyhow::Ok(tracks.items);
-                }
+            tokio::select! {
+                _ = checks.tick() => {}
+                _ = self.shutdown.cancelled() => return,
+            }
+            // Nothing to keep before the first login.
+            if self.session().username().is_empty() {
+                continue;
+            }
+            let result = if self.session().is_invalid() {
+                log::warn!("Spotify session dropped, reconnecting");
+                self.health.send_replace(SessionHealth::Reconnecting);
+                self.reconnect().await
+            } else if self.web_token_expires_soon().await {
+                let stale = self.web_access_token().await;
+                self.refresh_web_token(stale).await
+            } else {
+                continue;
+            };
+            let health = match result {
+                Ok(()) => SessionHealth::Connected,
                 Err(e) => {
-                    if self.requires_refresh(e).await {
-                        continue;
-                    }
-                    break Err(anyhow::anyhow!("Failed to refresh client"));
+                    log::error!("Failed to keep the session alive: {}", e);
+                    SessionHealth::Disconnected
                 }
+            };
+            self.health.send_replace(health);
+        }
+    }
+
+    /// Replaces the dropped session with a new one logged in with the saved credentials, and
+    /// the local and the Spotify Connect player with ones streaming over it.
+    async fn reconnect(&self) -> error::Result<()> {
+        let old = self.session();
+        let creds = old
+            .cache()
+            .and_then(|cache| cache.credentials())
+            .ok_or_else(|| Error::Auth("No saved login".into()))?;
+        let session = Session::new(
+            self.session_config.clone(),
+            old.cache().map(|cache| Cache::clone(cache)),
+        );
+        match &self.connect {
+            // Spirc connects the session itself, like on the first login.
+            Some(connect) => {
+                let player = new_player(
+                    &self.player_config,
+                    session.clone(),
+                    &self.mixer,
+                    &self.output,
+                );
+                connect
+                    .restart(session.clone(), creds, player, self.mixer.clone())
+                    .await?
             }
+            None => session.connect(creds, true).await?,
         }
+        let player = new_player(
+            &self.player_config,
+            session.clone(),
+            &self.mixer,
+            &self.output,
+        );
+        *self.session.write().unwrap() = session;
+        self.player.send_replace(player);
+        self.web_auth().await
     }
 
-    pub fn load_track(&self, id: String) -> Result<(), Error> {
-        let track_id = SpotifyId::from_uri(&format!("spotify:track:{}", id))?;
-        self.player.load(track_id, false, 0);
-        log::info!("Loaded track {}", id);
-        Ok(())
+    /// Calls `callback` with the current session health and every change to it.
+    pub async fn on_session_health<F>(&self, callback: F)
+    where
+        F: Fn(SessionHealth),
+    {
+        let mut health = self.health.subscribe();
+        loop {
+            callback(*health.borrow_and_update());
+            if health.changed().await.is_err() {
+                break;
+            }
+        }
     }
 
-    pub async fn auth(&self) -> Result<(), Error> {
+    pub async fn auth(&self) -> error::Result<()> {
         let c = librespot_oauth::OAuthClientBuilder::new(
             SPOTIFY_CLIENT_ID,
             "http://127.0.0.1:8898/login",
@@ -222,22 +829,27 @@ impl SpotifyService {
         )
         .open_in_browser()
         .build()
-        .map_err(|e| Error::unauthenticated(format!("Failed to run oAuth {}", e)))?
+        .map_err(|e| Error::Auth(format!("Failed to run OAuth: {}", e)))?
         .get_access_token_async()
         .await
         .map(|t| Credentials::with_access_token(t.access_token))
-        .map_err(|e| Error::unauthenticated(format!("Failed to authenticate: {}", e)))?;
-        self.connect(c)
-            .await
-            .map_err(|e| Error::unauthenticated(format!("Failed to authenticate: {}", e)))?;
+        .map_err(|e| Error::Auth(e.to_string()))?;
+        self.connect(c).await?;
         Ok(())
     }
+    /// Calls `callback` with the events of the local and the Spotify Connect player.
     pub async fn on_player_event<F>(&self, callback: F)
     where
         F: Fn(librespot_playback::player::PlayerEvent),
     {
-        while let Some(e) = self.player.get_player_event_channel().recv().await {
-            callback(e);
+        let local = follow_player(self.player.subscribe(), self.shutdown.clone());
+        let remote = match &self.connect {
+            Some(connect) => follow_player(connect.players(), self.shutdown.clone()).left_stream(),
+            None => futures::stream::empty().right_stream(),
+        };
+        let mut events = std::pin::pin!(futures::stream::select(local, remote));
+        while let Some(event) = events.next().await {
+            callback(event);
         }
     }
     pub async fn fetch_cover_art(
@@ -253,28 +865,570 @@ impl SpotifyService {
             img.height(),
         ))
     }
-    async fn requires_refresh(&self, e: ClientError) -> bool {
-        if let ClientError::Http(e) = e {
-            if let HttpError::StatusCode(res) = *e {
-                if res.status() == 401 {
-                    self.web_auth().await.unwrap_or_else(|e| {
-                        log::error!("Failed to refresh client: {}", e);
-                    });
-                    return true;
-                }
-                if res.status() == 429 {
-                    let wait = res.headers().get("Retry-After").and_then(|v| {
-                        v.to_str()
-                            .expect("Failed to convert header to string, fatal")
-                            .parse::<u64>()
-                            .ok()
-                    });
-                    log::debug!("rate limit hit, waiting for {}", wait.unwrap_or_default());
-                    tokio::time::sleep(Duration::from_secs(wait.unwrap_or_default())).await;
-                    return true;
+    /// Runs a Web API request, retrying it along `self.retry` after rate limits and transient
+    /// failures. An expired token is refreshed once per request.
+    async fn web_api<T, F, Fut>(&self, request: F) -> error::Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = ClientResult<T>>,
+    {
+        let mut refreshed = false;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let token = self.web_access_token().await;
+            let result = tokio::select! {
+                result = request() => result,
+                _ = self.shutdown.cancelled() => return Err(Error::Cancelled),
+            };
+            let e = match result {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            let expired = status(&e) == Some(401);
+            let transient = retry::is_transient(&e);
+            let e = Error::from_client(e).await;
+            if expired && !refreshed {
+                refreshed = true;
+                self.refresh_web_token(token).await?;
+                continue;
+            }
+            let Some(delay) = self.retry.delay(attempt, &e, transient) else {
+                return Err(e);
+            };
+            log::warn!("Web API request failed ({}), retrying in {:?}", e, delay);
+            retry::wait(delay, &self.shutdown).await?;
+        }
+    }
+}
+
+impl Backend for SpotifyService {
+    fn init(&self) -> BoxFuture<'_, error::Result<()>> {
+        Box::pin(SpotifyService::init(self))
+    }
+
+    fn auth(&self) -> BoxFuture<'_, error::Result<()>> {
+        Box::pin(SpotifyService::auth(self))
+    }
+
+    fn liked_songs_uri(&self) -> String {
+        format!("spotify:user:{}:collection", self.session().username())
+    }
+
+    fn user_playlists(&self) -> Pages<'_, SimplifiedPlaylist> {
+        SpotifyService::user_playlists(self).boxed()
+    }
+
+    fn playlist_items(&self, id: PlaylistId<'static>) -> Pages<'_, PlaylistItem> {
+        SpotifyService::playlist_items(self, id).boxed()
+    }
+
+    fn saved_tracks(&self) -> Pages<'_, SavedTrack> {
+        SpotifyService::saved_tracks(self).boxed()
+    }
+
+    fn fetch_cover_art(
+        &self,
+        url: String,
+    ) -> BoxFuture<'_, anyhow::Result<slint::SharedPixelBuffer<slint::Rgba8Pixel>>> {
+        Box::pin(SpotifyService::fetch_cover_art(self, url))
+    }
+
+    fn play(&self) {
+        if self.remote_command(RemoteCommand::Play) {
+            return;
+        }
+        match self.remote() {
+            Some(spirc) => log_remote_error(spirc.play()),
+            None => self.player().play(),
+        }
+    }
+
+    fn pause(&self) {
+        if self.remote_command(RemoteCommand::Pause) {
+            return;
+        }
+        match self.remote() {
+            Some(spirc) => log_remote_error(spirc.pause()),
+            None => self.player().pause(),
+        }
+    }
+
+    fn seek(&self, position_ms: u32) {
+        if self.remote_command(RemoteCommand::Seek(position_ms)) {
+            return;
+        }
+        match self.remote() {
+            Some(spirc) => log_remote_error(spirc.set_position_ms(position_ms)),
+            None => self.player().seek(position_ms),
+        }
+    }
+
+    fn next(&self) {
+        if self.remote_command(RemoteCommand::Next) {
+            return;
+        }
+        if let Some(spirc) = self.remote() {
+            log_remote_error(spirc.next());
+            return;
+        }
+        let next = self.queue.lock().unwrap().skip();
+        match next {
+            Some(track_id) => self.player().load(track_id, true, 0),
+            None => log::info!("Reached the end of the queue"),
+        }
+    }
+
+    fn previous(&self) {
+        if self.remote_command(RemoteCommand::Previous) {
+            return;
+        }
+        if let Some(spirc) = self.remote() {
+            log_remote_error(spirc.prev());
+            return;
+        }
+        let prev = self.queue.lock().unwrap().previous();
+        match prev {
+            Some(track_id) => self.player().load(track_id, true, 0),
+            None => self.player().seek(0),
+        }
+    }
+
+    fn volume(&self) -> u16 {
+        self.mixer.volume()
+    }
+
+    /// Sets the soft volume and remembers it for the next start.
+    fn set_volume(&self, volume: u16) {
+        let percent = (volume as u32 * 100 / u16::MAX as u32) as u8;
+        if self.remote_command(RemoteCommand::Volume(percent)) {
+            return;
+        }
+        if let Some(spirc) = self.remote() {
+            log_remote_error(spirc.set_volume(volume));
+            return;
+        }
+        self.mixer.set_volume(volume);
+        if let Some(cache) = self.session().cache() {
+            cache.save_volume(volume);
+        }
+        self.player().emit_volume_changed_event(volume);
+    }
+
+    fn shuffle(&self) -> bool {
+        match self.remote_device() {
+            Some(device) => device.shuffle,
+            None => match &self.connect {
+                Some(connect) if connect.remote().is_some() => connect.shuffle(),
+                _ => self.queue.lock().unwrap().shuffle(),
+            },
+        }
+    }
+
+    fn set_shuffle(&self, shuffle: bool) {
+        if self.remote_command(RemoteCommand::Shuffle(shuffle)) {
+            return;
+        }
+        if let Some(spirc) = self.remote() {
+            log_remote_error(spirc.shuffle(shuffle));
+            return;
+        }
+        self.queue.lock().unwrap().set_shuffle(shuffle);
+        self.player().emit_shuffle_changed_event(shuffle);
+    }
+
+    fn repeat(&self) -> RepeatMode {
+        match self.remote_device() {
+            Some(device) => device.repeat,
+            None => match &self.connect {
+                Some(connect) if connect.remote().is_some() => connect.repeat(),
+                _ => self.queue.lock().unwrap().repeat(),
+            },
+        }
+    }
+
+    fn set_repeat(&self, repeat: RepeatMode) {
+        if self.remote_command(RemoteCommand::Repeat(repeat)) {
+            return;
+        }
+        if let Some(spirc) = self.remote() {
+            log_remote_error(spirc.repeat(repeat != RepeatMode::Off));
+            log_remote_error(spirc.repeat_track(repeat == RepeatMode::Track));
+            return;
+        }
+        self.queue.lock().unwrap().set_repeat(repeat);
+        self.player()
+            .emit_repeat_changed_event(repeat != RepeatMode::Off, repeat == RepeatMode::Track);
+    }
+
+    /// Replaces the queue and starts playing from `start`.
+    fn play_queue(&self, tracks: Vec<SpotifyId>, start: usize, context_uri: Option<String>) {
+        self.play_queue_at(tracks, start, context_uri, 0);
+    }
+
+    fn enqueue(&self, track_id: SpotifyId) {
+        self.queue.lock().unwrap().push(track_id);
+    }
+
+    /// The local queue: the current item and what plays after it.
+    fn queue(&self) -> (Option<SpotifyId>, Vec<SpotifyId>) {
+        let queue = self.queue.lock().unwrap();
+        (queue.current(), queue.upcoming())
+    }
+
+    fn remove_from_queue(&self, index: usize) -> Option<SpotifyId> {
+        self.queue.lock().unwrap().remove_upcoming(index)
+    }
+
+    fn current_context(&self) -> Option<String> {
+        self.queue.lock().unwrap().context_uri().map(String::from)
+    }
+
+    /// Describes the file the player picks for `files` at the configured bitrate.
+    fn streaming_quality(&self, files: &AudioFiles) -> Option<String> {
+        quality::streaming_format(self.bitrate, files).map(quality::describe)
+    }
+
+    fn drive(&self) -> BoxFuture<'_, ()> {
+        Box::pin(async {
+            futures::join!(
+                self.drive_queue(),
+                self.drive_connect(),
+                self.keep_session()
+            );
+        })
+    }
+
+    fn on_player_event<'a>(
+        &'a self,
+        callback: Box<dyn Fn(PlayerEvent) + Send + Sync + 'a>,
+    ) -> BoxFuture<'a, ()> {
+        Box::pin(SpotifyService::on_player_event(self, callback))
+    }
+
+    fn as_spotify(&self) -> Option<&SpotifyService> {
+        Some(self)
+    }
+}
+
+/// The events of the player in `players`, moving on to its replacement after a reconnect, until
+/// `shutdown`. A player that stopped is not listened to again until it has been replaced.
+fn follow_player(
+    mut players: tokio::sync::watch::Receiver<Arc<Player>>,
+    shutdown: CancellationToken,
+) -> impl Stream<Item = PlayerEvent> + Send {
+    let events = players.borrow_and_update().get_player_event_channel();
+    futures::stream::unfold((players, events), move |(mut players, mut events)| {
+        let shutdown = shutdown.clone();
+        async move {
+            loop {
+                tokio::select! {
+                    Some(event) = events.recv() => return Some((event, (players, events))),
+                    changed = players.changed() => changed.ok()?,
+                    _ = shutdown.cancelled() => return None,
                 }
+                events = players.borrow_and_update().get_player_event_channel();
             }
         }
-        false
+    })
+}
+
+/// The local player, writing to whichever device `output` currently points at.
+fn new_player(
+    config: &PlayerConfig,
+    session: Session,
+    mixer: &SoftMixer,
+    output: &Arc<Output>,
+) -> Arc<Player> {
+    let output = output.clone();
+    Player::new(
+        config.clone(),
+        session,
+        mixer.get_soft_volume(),
+        move || output.sink(),
+    )
+}
+
+/// The HTTP status Spotify answered a failed request with.
+fn status(e: &ClientError) -> Option<u16> {
+    match e {
+        ClientError::Http(e) => match e.as_ref() {
+            HttpError::StatusCode(res) => Some(res.status().as_u16()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn log_remote_error(result: Result<(), librespot_core::Error>) {
+    if let Err(e) = result {
+        log::error!("Failed to send Spotify Connect command: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use futures::TryStreamExt;
+    use hyper::Method;
+    use rspotify::model::PlaylistId;
+
+    use super::*;
+    use crate::services::mock_web_api::{Failure, MockWebApi};
+
+    const OFFLINE_MIX: &str = "0000000000000000000001";
+
+    fn token(access_token: &str) -> rspotify::Token {
+        rspotify::Token {
+            access_token: access_token.into(),
+            ..Default::default()
+        }
+    }
+
+    /// A service talking to `api`, with short retries and a count of the token refreshes.
+    async fn service(api: &MockWebApi) -> (SpotifyService, Arc<AtomicUsize>) {
+        let mut settings = Settings::default();
+        settings.connect.enabled = false;
+        // Nothing is played, the pipe backend just keeps the player off the sound card.
+        settings.audio.backend = Some("pipe".into());
+        settings.audio.device = Some("/dev/null".into());
+        settings.network.web_api_url = Some(api.base_url());
+        let mut spotify = SpotifyService::new(&settings, CancellationToken::new()).unwrap();
+        spotify.retry = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(100),
+        };
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let counter = refreshes.clone();
+        spotify.web_tokens = Some(Arc::new(move || {
+            token(&format!(
+                "refreshed-{}",
+                counter.fetch_add(1, Ordering::SeqCst) + 1
+            ))
+        }));
+        spotify.set_web_token(token("initial")).await;
+        (spotify, refreshes)
+    }
+
+    fn authorizations(api: &MockWebApi) -> Vec<String> {
+        api.received()
+            .into_iter()
+            .map(|request| request.authorization.unwrap_or_default())
+            .collect()
+    }
+
+    fn silent_player() -> Arc<Player> {
+        let output = Output::new(
+            Some("pipe".into()),
+            Some("/dev/null".into()),
+            Default::default(),
+        );
+        new_player(
+            &PlayerConfig::default(),
+            Session::new(SessionConfig::default(), None),
+            &SoftMixer::open(MixerConfig::default()).unwrap(),
+            &Arc::new(output),
+        )
+    }
+
+    #[tokio::test]
+    async fn follow_player_moves_on_to_the_replacement() {
+        let (players, receiver) = tokio::sync::watch::channel(silent_player());
+        let shutdown = CancellationToken::new();
+        let mut events = std::pin::pin!(follow_player(receiver, shutdown.clone()));
+
+        players.borrow().emit_volume_changed_event(1);
+        assert!(matches!(
+            tokio::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .expect("No player event"),
+            Some(PlayerEvent::VolumeChanged { volume: 1 })
+        ));
+        // Dropping the old player closes its channel, the stream waits for and then goes on
+        // with the new one. Events sent before it subscribed to that one are missed.
+        players.send_replace(silent_player());
+        let idle = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+        assert!(idle.is_err(), "{:?}", idle);
+        players.borrow().emit_volume_changed_event(2);
+        assert!(matches!(
+            tokio::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .expect("No player event"),
+            Some(PlayerEvent::VolumeChanged { volume: 2 })
+        ));
+        shutdown.cancel();
+        assert!(
+            tokio::time::timeout(Duration::from_secs(5), events.next())
+                .await
+                .expect("No player event")
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn paginate_walks_every_page() {
+        let api = MockWebApi::start().await.unwrap();
+        api.set_saved_track_count(120);
+        api.set_playlist_item_count(OFFLINE_MIX, 250);
+        let (spotify, _) = service(&api).await;
+
+        let pages: Vec<_> = spotify.saved_tracks().try_collect().await.unwrap();
+        assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [50, 50, 20]);
+        assert_eq!(pages[2][19].track.name, "Track 120");
+        let playlist = PlaylistId::from_id(OFFLINE_MIX).unwrap();
+        let pages: Vec<_> = spotify
+            .playlist_items(playlist)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            pages.iter().map(Vec::len).collect::<Vec<_>>(),
+            [100, 100, 50]
+        );
+
+        let received = api.received();
+        assert!(received.iter().all(|request| request.method == Method::GET));
+        let offsets: Vec<_> = received
+            .iter()
+            .map(|request| {
+                let query = request.uri.split_once('?').unwrap().1;
+                let offset = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("offset="));
+                offset.unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(offsets, ["0", "50", "100", "0", "100", "200"]);
+    }
+
+    #[tokio::test]
+    async fn paginate_moves_on_despite_a_zero_limit() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, _) = service(&api).await;
+        let page = |offset: u32, items: Vec<u32>| Page {
+            href: String::new(),
+            items,
+            limit: 0,
+            next: Some("next".to_string()),
+            offset,
+            previous: None,
+            total: 5,
+        };
+
+        let pages: Vec<_> = spotify
+            .paginate(PAGE_SIZE, |_, offset| async move {
+                Ok(if offset < 3 {
+                    page(offset, vec![offset])
+                } else {
+                    page(offset, Vec::new())
+                })
+            })
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(pages, [vec![0], vec![1], vec![2], vec![]]);
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_refreshed_once() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, refreshes) = service(&api).await;
+        api.fail_next(Failure::unauthorized());
+
+        let pages: Vec<_> = spotify.saved_tracks().try_collect().await.unwrap();
+        assert_eq!(pages.concat().len(), 2);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            authorizations(&api),
+            ["Bearer initial", "Bearer refreshed-1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshed_token_turned_down_is_an_auth_error() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, refreshes) = service(&api).await;
+        api.fail_next(Failure::unauthorized());
+        api.fail_next(Failure::unauthorized());
+
+        let result: error::Result<Vec<_>> = spotify.saved_tracks().try_collect().await;
+        assert!(matches!(result, Err(Error::Auth(_))), "{:?}", result);
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+        assert_eq!(api.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_are_waited_out() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, _) = service(&api).await;
+        api.fail_next(Failure::rate_limited(0));
+        api.fail_next(Failure::rate_limited(0));
+
+        let pages: Vec<_> = spotify.saved_tracks().try_collect().await.unwrap();
+        assert_eq!(pages.concat().len(), 2);
+        assert_eq!(api.received().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_surfaces_once_attempts_run_out() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, _) = service(&api).await;
+        for _ in 0..3 {
+            api.fail_next(Failure::rate_limited(0));
+        }
+
+        let result: error::Result<Vec<_>> = spotify.saved_tracks().try_collect().await;
+        assert!(
+            matches!(
+                result,
+                Err(Error::RateLimited {
+                    retry_after: Some(0)
+                })
+            ),
+            "{:?}",
+            result
+        );
+        assert_eq!(api.received().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn retry_after_beyond_the_cap_is_not_waited_out() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, _) = service(&api).await;
+        api.fail_next(Failure::rate_limited(60));
+
+        let result: error::Result<Vec<_>> = spotify.saved_tracks().try_collect().await;
+        assert!(
+            matches!(
+                result,
+                Err(Error::RateLimited {
+                    retry_after: Some(60)
+                })
+            ),
+            "{:?}",
+            result
+        );
+        assert_eq!(api.received().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn server_errors_are_retried() {
+        let api = MockWebApi::start().await.unwrap();
+        let (spotify, _) = service(&api).await;
+        api.fail_next(Failure::server_error(502));
+        api.fail_next(Failure::server_error(503));
+
+        let pages: Vec<_> = spotify.saved_tracks().try_collect().await.unwrap();
+        assert_eq!(pages.concat().len(), 2);
+        assert_eq!(api.received().len(), 3);
+
+        for _ in 0..3 {
+            api.fail_next(Failure::server_error(500));
+        }
+        let result: error::Result<Vec<_>> = spotify.saved_tracks().try_collect().await;
+        assert!(matches!(result, Err(Error::Network(_))), "{:?}", result);
+        assert_eq!(api.received().len(), 6);
     }
 }
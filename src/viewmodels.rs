@@ -1,15 +1,189 @@
+pub mod album_vm;
+pub mod artist_vm;
 pub mod authentication_vm;
+pub mod devices_vm;
+pub mod lyrics_vm;
+#[cfg(target_os = "linux")]
+pub mod mpris_vm;
 pub mod player_vm;
+pub mod playlists_vm;
+pub mod search_vm;
+pub mod settings_vm;
+pub mod shows_vm;
+pub mod tracks_vm;
 pub mod utils;
 pub mod window_vm;
-pub mod tracks_vm;
 
 pub fn init() -> anyhow::Result<()> {
     window_vm::register_handlers()?;
+    album_vm::register_handlers()?;
+    artist_vm::register_handlers()?;
     authentication_vm::init();
     authentication_vm::register_handlers()?;
+    devices_vm::register_handlers()?;
     player_vm::register_handlers()?;
+    #[cfg(target_os = "linux")]
+    mpris_vm::register_handlers()?;
+    playlists_vm::register_handlers()?;
+    search_vm::register_handlers()?;
+    settings_vm::register_handlers()?;
+    shows_vm::register_handlers()?;
     tracks_vm::register_handlers()?;
     utils::register_handlers()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    use librespot_core::SpotifyId;
+    use slint::platform::software_renderer::{MinimalSoftwareWindow, RepaintBufferType};
+    use slint::platform::{EventLoopProxy, Platform, WindowAdapter};
+    use slint::{ComponentHandle, Model};
+
+    use super::*;
+    use crate::services::{self, backend, fake::FakeBackend, settings::Settings};
+
+    type Posted = Arc<Mutex<VecDeque<Box<dyn FnOnce() + Send>>>>;
+
+    /// A platform without a window system, holding what other threads post to the event loop
+    /// until the test runs it.
+    struct TestPlatform(Posted);
+
+    impl Platform for TestPlatform {
+        fn create_window_adapter(&self) -> Result<Rc<dyn WindowAdapter>, slint::PlatformError> {
+            Ok(MinimalSoftwareWindow::new(RepaintBufferType::NewBuffer))
+        }
+
+        fn new_event_loop_proxy(&self) -> Option<Box<dyn EventLoopProxy>> {
+            Some(Box::new(TestProxy(self.0.clone())))
+        }
+    }
+
+    struct TestProxy(Posted);
+
+    impl EventLoopProxy for TestProxy {
+        fn quit_event_loop(&self) -> Result<(), slint::EventLoopError> {
+            Ok(())
+        }
+
+        fn invoke_from_event_loop(
+            &self,
+            event: Box<dyn FnOnce() + Send>,
+        ) -> Result<(), slint::EventLoopError> {
+            self.0.lock().unwrap().push_back(event);
+            Ok(())
+        }
+    }
+
+    /// Runs posted UI updates until `done` holds.
+    fn pump_until(posted: &Posted, what: &str, done: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !done() {
+            assert!(Instant::now() < deadline, "Timed out waiting for {}", what);
+            let pending: Vec<_> = posted.lock().unwrap().drain(..).collect();
+            for event in pending {
+                event();
+            }
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// The base62 id `FakeBackend::sample` gives its `n`th track and its playlist.
+    fn sample_id(n: u32) -> String {
+        format!("{:0>22}", n)
+    }
+
+    fn sample_track(n: u32) -> SpotifyId {
+        SpotifyId::from_uri(&format!("spotify:track:{}", sample_id(n))).unwrap()
+    }
+
+    /// The services are process wide, so the whole session runs in one test.
+    #[test]
+    fn offline_session() {
+        let posted = Posted::default();
+        slint::platform::set_platform(Box::new(TestPlatform(posted.clone()))).unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let ui = crate::MainWindow::new().unwrap();
+        services::init(
+            FakeBackend::sample(),
+            Settings::default(),
+            rt.handle().clone(),
+            Some(ui.as_weak()),
+        );
+        authentication_vm::init();
+        authentication_vm::register_handlers().unwrap();
+        player_vm::register_handlers().unwrap();
+        tracks_vm::register_handlers().unwrap();
+        let auth = ui.global::<crate::AuthenticationState>();
+        let player = ui.global::<crate::PlayerState>();
+        let tracks = ui.global::<crate::TracksState>();
+
+        // Logging in: restored at startup, then again through the button.
+        pump_until(&posted, "the restored login", || {
+            auth.get_loggedIn() && !auth.get_loading()
+        });
+        auth.set_loggedIn(false);
+        auth.invoke_login_clicked();
+        pump_until(&posted, "the login", || {
+            auth.get_loggedIn() && !auth.get_login_in_progress()
+        });
+        assert_eq!(auth.get_error(), "");
+
+        // Playing the second track of the playlist queues the rest of it.
+        pump_until(&posted, "the track handlers", || {
+            posted.lock().unwrap().is_empty()
+        });
+        tracks.invoke_fetch_tracks(sample_id(1).into());
+        pump_until(&posted, "the playlist", || {
+            !tracks.get_fetching_tracks() && tracks.get_tracks().row_count() == 4
+        });
+        tracks.invoke_track_clicked(sample_id(2).into(), 1);
+        pump_until(&posted, "the clicked track", || {
+            tracks.get_current_track_id() == sample_id(2).as_str() && player.get_is_playing()
+        });
+        assert_eq!(
+            backend().queue(),
+            (
+                Some(sample_track(2)),
+                vec![sample_track(3), sample_track(4)]
+            )
+        );
+        assert_eq!(
+            backend().current_context(),
+            Some(format!("spotify:playlist:{}", sample_id(1)))
+        );
+
+        player.invoke_pause();
+        pump_until(&posted, "the pause", || !player.get_is_playing());
+        player.invoke_play();
+        pump_until(&posted, "the resume", || player.get_is_playing());
+
+        player.invoke_next_clicked();
+        pump_until(&posted, "the next track", || {
+            tracks.get_current_track_id() == sample_id(3).as_str()
+        });
+        assert_eq!(
+            backend().queue(),
+            (Some(sample_track(3)), vec![sample_track(4)])
+        );
+
+        // Early in a track previous goes back, further in it restarts the track.
+        player.invoke_previous_clicked();
+        pump_until(&posted, "the previous track", || {
+            tracks.get_current_track_id() == sample_id(2).as_str()
+        });
+        player.invoke_seek(10_000);
+        pump_until(&posted, "the seek", || player.get_current_time() >= 10_000);
+        player.invoke_previous_clicked();
+        pump_until(&posted, "the restart", || {
+            player.get_current_time() < 10_000
+        });
+        assert_eq!(tracks.get_current_track_id(), sample_id(2).as_str());
+        assert_eq!(backend().queue().0, Some(sample_track(2)));
+    }
+}
@@ -0,0 +1,122 @@
+use futures::StreamExt;
+use rspotify::model::AlbumId;
+use slint::{ComponentHandle, Model};
+
+use crate::models::{self, album, toast};
+use crate::services::{backend, rt, ui_weak};
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::AlbumState>();
+    app.on_open_album(|id| {
+        open_album(id.into());
+    });
+    app.on_play_album(|| {
+        play_album(0);
+    });
+    app.on_track_clicked(|index| {
+        play_album(index as usize);
+    });
+    app.on_toggle_saved(|| {
+        let ui = ui_weak().unwrap();
+        let state = ui.global::<crate::AlbumState>();
+        let id = state.get_id().to_string();
+        let saved = !state.get_saved();
+        let Some(spotify) = backend().as_spotify() else {
+            album::set_error("Your library is only available on Spotify".into()).unwrap();
+            return;
+        };
+        rt().spawn(async move {
+            let album_id = match AlbumId::from_id(id.as_str()) {
+                Ok(album_id) => album_id,
+                Err(e) => {
+                    log::error!("Invalid album id {}: {}", id, e);
+                    return;
+                }
+            };
+            match spotify.set_album_saved(album_id, saved).await {
+                Ok(()) => album::set_saved(id, saved).unwrap(),
+                Err(e) => {
+                    log::error!("Failed to update saved album {}: {:#}", id, e);
+                    album::set_error(format!("Failed to update your library: {:#}", e)).unwrap();
+                }
+            }
+        });
+    });
+    Ok(())
+}
+
+/// Opens the album page of `id`, loading its details and tracks.
+pub fn open_album(id: String) {
+    log::info!("Opening album {}", id);
+    album::open(id.clone()).unwrap();
+    let Some(spotify) = backend().as_spotify() else {
+        album::set_error("Albums are only available on Spotify".into()).unwrap();
+        return;
+    };
+    album::set_fetching(true).unwrap();
+    rt().spawn(async move {
+        let album_id = match AlbumId::from_id(id.clone()) {
+            Ok(album_id) => album_id,
+            Err(e) => {
+                log::error!("Invalid album id {}: {}", id, e);
+                album::set_fetching(false).unwrap();
+                return;
+            }
+        };
+        match spotify.album(album_id.clone()).await {
+            Ok(details) => {
+                if let Some(url) = models::large_image(&details.images) {
+                    let id = id.clone();
+                    let url = url.to_string();
+                    rt().spawn(async move {
+                        match backend().fetch_cover_art(url).await {
+                            Ok(img) => album::set_cover_art(id, img).unwrap(),
+                            Err(e) => log::error!("Failed to fetch album cover: {}", e),
+                        }
+                    });
+                }
+                album::set_details(details).unwrap();
+            }
+            Err(e) => {
+                log::error!("Failed to fetch album {}: {:#}", id, e);
+                album::set_error(format!("Failed to fetch album: {:#}", e)).unwrap();
+                album::set_fetching(false).unwrap();
+                return;
+            }
+        }
+        match spotify.is_album_saved(album_id.clone()).await {
+            Ok(saved) => album::set_saved(id.clone(), saved).unwrap(),
+            Err(e) => log::error!("Failed to check saved album {}: {:#}", id, e),
+        }
+        let mut pages = std::pin::pin!(spotify.album_tracks(album_id));
+        while let Some(page) = pages.next().await {
+            match page {
+                Ok(tracks) => album::add_tracks(id.clone(), tracks).unwrap(),
+                Err(e) => {
+                    log::error!("Failed to fetch album tracks: {:#}", e);
+                    toast::show_error(&e).unwrap();
+                    break;
+                }
+            }
+        }
+        album::set_fetching(false).unwrap();
+    });
+}
+
+/// Plays the open album as a context, starting from track `start`.
+fn play_album(start: usize) {
+    let ui = ui_weak().unwrap();
+    let state = ui.global::<crate::AlbumState>();
+    let ids: Vec<String> = state.get_tracks().iter().map(|t| t.id.into()).collect();
+    let Some(current) = ids.get(start) else {
+        return;
+    };
+    let context_uri = format!("spotify:album:{}", state.get_id());
+    if let Err(e) = backend().play_context(context_uri, &ids, start) {
+        log::error!("Failed to play album: {:#}", e);
+        return;
+    }
+    ui.global::<crate::TracksState>()
+        .set_current_track_id(current.as_str().into());
+}
@@ -0,0 +1,167 @@
+use futures::StreamExt;
+use rspotify::model::{ArtistId, Image};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::models::{self, artist, toast};
+use crate::services::{backend, rt, ui_weak};
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::ArtistState>();
+    app.on_open_artist(|id| {
+        open_artist(id.into());
+    });
+    app.on_play_top_tracks(|| {
+        play_top_tracks(0);
+    });
+    app.on_track_clicked(|index| {
+        play_top_tracks(index as usize);
+    });
+    app.on_toggle_following(|| {
+        let ui = ui_weak().unwrap();
+        let state = ui.global::<crate::ArtistState>();
+        let id = state.get_id().to_string();
+        let follow = !state.get_following();
+        let Some(spotify) = backend().as_spotify() else {
+            artist::set_error("Following artists is only available on Spotify".into()).unwrap();
+            return;
+        };
+        rt().spawn(async move {
+            let artist_id = match ArtistId::from_id(id.as_str()) {
+                Ok(artist_id) => artist_id,
+                Err(e) => {
+                    log::error!("Invalid artist id {}: {}", id, e);
+                    return;
+                }
+            };
+            match spotify.set_following_artist(artist_id, follow).await {
+                Ok(()) => artist::set_following(id, follow).unwrap(),
+                Err(e) => {
+                    log::error!("Failed to update followed artist {}: {:#}", id, e);
+                    artist::set_error(format!("Failed to update followed artists: {:#}", e))
+                        .unwrap();
+                }
+            }
+        });
+    });
+    Ok(())
+}
+
+/// Opens the artist page of `id`, loading its top tracks, releases and related artists.
+pub fn open_artist(id: String) {
+    log::info!("Opening artist {}", id);
+    artist::open(id.clone()).unwrap();
+    let Some(spotify) = backend().as_spotify() else {
+        artist::set_error("Artists are only available on Spotify".into()).unwrap();
+        return;
+    };
+    let artist_id = match ArtistId::from_id(id.clone()) {
+        Ok(artist_id) => artist_id,
+        Err(e) => {
+            log::error!("Invalid artist id {}: {}", id, e);
+            return;
+        }
+    };
+    artist::set_fetching(true).unwrap();
+    let (details_id, details_artist) = (id.clone(), artist_id.clone());
+    rt().spawn(async move {
+        match spotify.artist(details_artist.clone()).await {
+            Ok(details) => {
+                if let Some(url) = models::large_image(&details.images).map(str::to_string) {
+                    let id = details_id.clone();
+                    rt().spawn(async move {
+                        match backend().fetch_cover_art(url).await {
+                            Ok(img) => artist::set_image(id, img).unwrap(),
+                            Err(e) => log::error!("Failed to fetch artist image: {}", e),
+                        }
+                    });
+                }
+                artist::set_details(details).unwrap();
+            }
+            Err(e) => {
+                log::error!("Failed to fetch artist {}: {:#}", details_id, e);
+                artist::set_error(format!("Failed to fetch artist: {:#}", e)).unwrap();
+            }
+        }
+        match spotify.is_following_artist(details_artist.clone()).await {
+            Ok(following) => artist::set_following(details_id.clone(), following).unwrap(),
+            Err(e) => log::error!("Failed to check followed artist {}: {:#}", details_id, e),
+        }
+        match spotify.artist_top_tracks(details_artist).await {
+            Ok(tracks) => artist::set_top_tracks(details_id, tracks).unwrap(),
+            Err(e) => log::error!("Failed to fetch top tracks: {:#}", e),
+        }
+        artist::set_fetching(false).unwrap();
+    });
+    let (albums_id, albums_artist) = (id.clone(), artist_id.clone());
+    rt().spawn(async move {
+        let mut pages = std::pin::pin!(spotify.artist_albums(albums_artist));
+        while let Some(page) = pages.next().await {
+            match page {
+                Ok(albums) => {
+                    let covers: Vec<_> = albums
+                        .iter()
+                        .filter_map(|album| {
+                            Some((album.id.as_ref()?.id().to_string(), album.images.clone()))
+                        })
+                        .collect();
+                    artist::add_albums(albums_id.clone(), albums).unwrap();
+                    for (album_id, images) in covers {
+                        fetch_card_art(album_id, &images);
+                    }
+                }
+                Err(e) => {
+                    log::error!("Failed to fetch artist albums: {:#}", e);
+                    toast::show_error(&e).unwrap();
+                    break;
+                }
+            }
+        }
+    });
+    rt().spawn(async move {
+        match spotify.related_artists(artist_id).await {
+            Ok(related) => {
+                let covers: Vec<_> = related
+                    .iter()
+                    .map(|artist| (artist.id.id().to_string(), artist.images.clone()))
+                    .collect();
+                artist::set_related(id, related).unwrap();
+                for (artist_id, images) in covers {
+                    fetch_card_art(artist_id, &images);
+                }
+            }
+            // The section stays hidden when the endpoint is unavailable.
+            Err(e) => log::warn!("Failed to fetch related artists: {:#}", e),
+        }
+    });
+}
+
+/// Plays the top tracks of the open artist, starting from `start`.
+fn play_top_tracks(start: usize) {
+    let ui = ui_weak().unwrap();
+    let state = ui.global::<crate::ArtistState>();
+    let ids: Vec<String> = state.get_top_tracks().iter().map(|t| t.id.into()).collect();
+    let Some(current) = ids.get(start) else {
+        return;
+    };
+    let context_uri = format!("spotify:artist:{}", state.get_id());
+    if let Err(e) = backend().play_context(context_uri, &ids, start) {
+        log::error!("Failed to play top tracks: {:#}", e);
+        return;
+    }
+    ui.global::<crate::TracksState>()
+        .set_current_track_id(current.as_str().into());
+}
+
+fn fetch_card_art(id: String, images: &[Image]) {
+    let Some(url) = models::thumbnail(images).map(str::to_string) else {
+        return;
+    };
+    rt().spawn(async move {
+        match backend().fetch_cover_art(url).await {
+            Ok(img) => artist::set_card_art(id, img).unwrap(),
+            Err(e) => log::error!("Failed to fetch artist page cover: {}", e),
+        }
+    });
+}
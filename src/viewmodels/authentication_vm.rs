@@ -2,7 +2,7 @@ use slint::ComponentHandle;
 
 use crate::{
     models::authentication,
-    services::{rt, spotify, ui_weak},
+    services::{backend, error::Error, rt, spotify, ui_weak},
 };
 
 pub fn register_handlers() -> anyhow::Result<()> {
@@ -16,26 +16,34 @@ pub fn register_handlers() -> anyhow::Result<()> {
 
 pub fn init() {
     rt().spawn(async {
-        spotify()
-            .init()
-            .await
-            .unwrap_or_else(|e| log::error!("Failed to init spotify client: {}", e));
-        if let Err(e) = spotify().web_auth().await {
-            log::error!("Failed to init spotify web api: {}", e);
-            authentication::login_failed("Auto Login Failed").unwrap();
+        if let Err(e) = backend().init().await {
+            log::error!("Failed to restore the login: {:#}", e);
+            // Having no saved login is the usual first start, not something to report.
+            let shown = (!matches!(e, Error::Auth(_))).then_some(&e);
+            authentication::login_failed(shown).unwrap();
         } else {
             log::info!("Successfuly logged in");
             authentication::login_succeeded().unwrap();
         }
     });
+    // Only Spotify keeps a session that can drop.
+    if backend().as_spotify().is_some() {
+        rt().spawn(async {
+            spotify()
+                .on_session_health(|health| {
+                    authentication::set_session_health(health).unwrap();
+                })
+                .await;
+        });
+    }
 }
 
 pub fn handle_login() {
     rt().spawn(async move {
         authentication::login_started().unwrap();
-        if let Err(e) = spotify().auth().await {
+        if let Err(e) = backend().auth().await {
             log::error!("Failed to login: {}", e);
-            authentication::login_failed("Failed to login").unwrap();
+            authentication::login_failed(Some(&e)).unwrap();
         } else {
             log::info!("Successfuly logged in");
             authentication::login_succeeded().unwrap();
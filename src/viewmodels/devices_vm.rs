@@ -0,0 +1,82 @@
+use slint::ComponentHandle;
+
+use crate::{
+    models::{devices, player},
+    services::{backend, rt, ui_weak},
+};
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::DevicesState>();
+    app.on_fetch_devices(|| {
+        rt().spawn(fetch_devices());
+    });
+    app.on_device_clicked(|id| {
+        log::info!("Device clicked: {}", id);
+        let Some(spotify) = backend().as_spotify() else {
+            return;
+        };
+        rt().spawn(async move {
+            match spotify.transfer_playback(id.into()).await {
+                Ok(()) => {
+                    if spotify.remote_device().is_none() {
+                        player::set_device_name("".into()).unwrap();
+                    }
+                    fetch_devices().await;
+                }
+                Err(e) => {
+                    log::error!("Failed to transfer playback: {:#}", e);
+                    devices::set_error(format!("Failed to transfer playback: {:#}", e)).unwrap();
+                }
+            }
+        });
+    });
+    Ok(())
+}
+
+async fn fetch_devices() {
+    let Some(spotify) = backend().as_spotify() else {
+        devices::set_error("Connect devices are only available on Spotify".into()).unwrap();
+        return;
+    };
+    devices::set_fetching(true).unwrap();
+    devices::set_error("".into()).unwrap();
+    match spotify.get_devices().await {
+        Ok(list) => {
+            let own_id = spotify.device_id();
+            let remote = spotify.remote_device();
+            let mut items: Vec<crate::Device> = list
+                .into_iter()
+                .map(|device| {
+                    let id = device.id.unwrap_or_default();
+                    crate::Device {
+                        local: id == own_id,
+                        active: remote.as_ref().is_some_and(|r| r.id == id) || device.is_active,
+                        kind: format!("{:?}", device._type).into(),
+                        name: device.name.into(),
+                        id: id.into(),
+                    }
+                })
+                .collect();
+            // Without our own receiver in the list there is still a way back to local playback.
+            if !items.iter().any(|device| device.local) {
+                items.insert(
+                    0,
+                    crate::Device {
+                        id: "".into(),
+                        name: "This device".into(),
+                        kind: "Computer".into(),
+                        active: remote.is_none(),
+                        local: true,
+                    },
+                );
+            }
+            devices::set_devices(items).unwrap();
+        }
+        Err(e) => {
+            log::error!("Failed to fetch devices: {:#}", e);
+            devices::set_error(format!("Failed to fetch devices: {:#}", e)).unwrap();
+        }
+    }
+    devices::set_fetching(false).unwrap();
+}
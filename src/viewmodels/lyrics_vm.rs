@@ -0,0 +1,36 @@
+use librespot_core::{SpotifyId, spotify_id::SpotifyItemType};
+
+use crate::models::lyrics;
+use crate::services::{self, backend, rt, settings};
+
+/// Loads the lyrics of the playing item, preferring Spotify's and falling back to a local
+/// .lrc file.
+pub fn fetch_lyrics(track_id: SpotifyId, title: String, artist: String) {
+    let Ok(id) = track_id.to_base62() else {
+        return;
+    };
+    lyrics::open_track(id.clone()).unwrap();
+    if track_id.item_type != SpotifyItemType::Track {
+        lyrics::set_lyrics(id, None).unwrap();
+        return;
+    }
+    rt().spawn(async move {
+        let found = match backend().as_spotify() {
+            Some(spotify) => match spotify.lyrics(track_id).await {
+                Ok(found) if !found.lines.is_empty() => Some(found),
+                Ok(_) => None,
+                // Spotify answers 404 for tracks without lyrics.
+                Err(e) => {
+                    log::info!("No Spotify lyrics for {}: {:#}", id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let found = found.or_else(|| {
+            let dir = settings().read().unwrap().lyrics_dir().ok()?;
+            services::lyrics::load_local(&dir, &id, &artist, &title)
+        });
+        lyrics::set_lyrics(id, found).unwrap();
+    });
+}
@@ -0,0 +1,136 @@
+use std::sync::OnceLock;
+
+use librespot_metadata::audio::UniqueFields;
+use librespot_playback::player::PlayerEvent;
+use tokio::sync::mpsc;
+
+use crate::services::mpris::{Command, Metadata, Mpris, PlaybackStatus};
+use crate::services::queue::RepeatMode;
+use crate::services::{backend, rt};
+
+/// Player events waiting to be mirrored to MPRIS, kept in order by a single task.
+static EVENTS: OnceLock<mpsc::UnboundedSender<PlayerEvent>> = OnceLock::new();
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    EVENTS.set(events_tx).ok();
+    rt().spawn(async move {
+        let (commands_tx, mut commands) = mpsc::unbounded_channel();
+        let mpris = match Mpris::start(commands_tx).await {
+            Ok(mpris) => mpris,
+            Err(e) => {
+                log::warn!("MPRIS is unavailable: {:#}", e);
+                return;
+            }
+        };
+        let mut playing = false;
+        loop {
+            tokio::select! {
+                Some(command) = commands.recv() => handle_command(command, playing),
+                Some(event) = events.recv() => {
+                    match &event {
+                        PlayerEvent::Playing { .. } => playing = true,
+                        PlayerEvent::Paused { .. } | PlayerEvent::Stopped { .. } => playing = false,
+                        _ => {}
+                    }
+                    if let Err(e) = mirror_event(&mpris, event).await {
+                        log::warn!("Failed to update MPRIS: {:#}", e);
+                    }
+                }
+                else => break,
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Forwards a player event to MPRIS clients.
+pub fn player_event(event: &PlayerEvent) {
+    if let Some(events) = EVENTS.get() {
+        events.send(event.clone()).ok();
+    }
+}
+
+async fn mirror_event(mpris: &Mpris, event: PlayerEvent) -> anyhow::Result<()> {
+    match event {
+        PlayerEvent::TrackChanged { audio_item } => {
+            let (artists, album) = match audio_item.unique_fields {
+                UniqueFields::Track { artists, album, .. } => {
+                    use librespot_protocol::metadata::artist_with_role::ArtistRole;
+                    let artists = artists
+                        .0
+                        .into_iter()
+                        .filter(|a| a.role == ArtistRole::ARTIST_ROLE_MAIN_ARTIST)
+                        .map(|a| a.name)
+                        .collect();
+                    (artists, album)
+                }
+                UniqueFields::Episode { show_name, .. } => (vec![show_name.clone()], show_name),
+            };
+            mpris
+                .set_metadata(Metadata {
+                    id: audio_item.track_id.to_base62().unwrap_or_default(),
+                    title: audio_item.name,
+                    artists,
+                    album,
+                    duration_ms: audio_item.duration_ms,
+                    art_url: audio_item.covers.first().map(|c| c.url.clone()),
+                })
+                .await
+        }
+        PlayerEvent::Playing { position_ms, .. } => {
+            mpris.set_position(position_ms).await?;
+            mpris.set_playback_status(PlaybackStatus::Playing).await
+        }
+        PlayerEvent::Paused { position_ms, .. } => {
+            mpris.set_position(position_ms).await?;
+            mpris.set_playback_status(PlaybackStatus::Paused).await
+        }
+        PlayerEvent::Stopped { .. } => mpris.set_playback_status(PlaybackStatus::Stopped).await,
+        PlayerEvent::Loading { position_ms, .. }
+        | PlayerEvent::PositionChanged { position_ms, .. } => mpris.set_position(position_ms).await,
+        PlayerEvent::PositionCorrection { position_ms, .. }
+        | PlayerEvent::Seeked { position_ms, .. } => mpris.seeked(position_ms).await,
+        PlayerEvent::VolumeChanged { volume } => {
+            mpris.set_volume(volume as f64 / u16::MAX as f64).await
+        }
+        PlayerEvent::ShuffleChanged { shuffle } => mpris.set_shuffle(shuffle).await,
+        PlayerEvent::RepeatChanged { context, track } => {
+            let repeat = match (context, track) {
+                (_, true) => RepeatMode::Track,
+                (true, false) => RepeatMode::Context,
+                (false, false) => RepeatMode::Off,
+            };
+            mpris.set_repeat(repeat).await
+        }
+        _ => Ok(()),
+    }
+}
+
+fn handle_command(command: Command, playing: bool) {
+    log::info!("MPRIS command: {:?}", command);
+    match command {
+        Command::Play => backend().play(),
+        Command::Pause | Command::Stop => backend().pause(),
+        Command::PlayPause if playing => backend().pause(),
+        Command::PlayPause => backend().play(),
+        Command::Next => backend().next(),
+        Command::Previous => backend().previous(),
+        Command::Seek(position_ms) => backend().seek(position_ms),
+        Command::Volume(volume) => backend().set_volume((volume * u16::MAX as f64) as u16),
+        Command::Shuffle(shuffle) => backend().set_shuffle(shuffle),
+        Command::Repeat(repeat) => backend().set_repeat(repeat),
+        Command::OpenUri(uri) => match uri.strip_prefix("spotify:track:") {
+            Some(id) => backend()
+                .load_track(id.to_string())
+                .unwrap_or_else(|e| log::error!("Failed to load track: {}", e)),
+            None => log::warn!("Cannot open {} over MPRIS", uri),
+        },
+        Command::Quit => {
+            slint::invoke_from_event_loop(|| {
+                super::window_vm::close().unwrap_or_else(|e| log::error!("Failed to quit: {}", e));
+            })
+            .unwrap_or_else(|e| log::error!("Failed to quit: {}", e));
+        }
+    }
+}
@@ -1,33 +1,112 @@
+use librespot_core::SpotifyId;
+use rspotify::model::EpisodeId;
 use slint::ComponentHandle;
 
 use crate::{
-    models::player,
-    services::{rt, spotify, ui_weak},
+    models::{self, player, toast, tracks},
+    services::{backend, error::Error, rt, spotify, ui_weak},
 };
 
+use super::lyrics_vm;
+
 pub fn register_handlers() -> anyhow::Result<()> {
     let ui = ui_weak().unwrap();
     let app = ui.global::<crate::PlayerState>();
     app.on_play(|| {
-        spotify().player.play();
+        backend().play();
     });
     app.on_pause(|| {
-        spotify().player.pause();
+        backend().pause();
     });
     app.on_seek(|pos| {
-        spotify().player.seek(pos as u32);
+        backend().seek(pos as u32);
+    });
+    app.on_next_clicked(|| {
+        backend().next();
+    });
+    app.on_previous_clicked(|| {
+        backend().previous();
+    });
+    app.set_volume(backend().volume() as f32 / u16::MAX as f32);
+    app.on_volume_changed(|volume| {
+        backend().set_volume((volume.clamp(0.0, 1.0) * u16::MAX as f32) as u16);
+    });
+    app.on_shuffle_clicked(|| {
+        backend().toggle_shuffle();
     });
+    app.on_repeat_clicked(|| {
+        backend().cycle_repeat();
+    });
+    // Output devices and remote playback only exist on Spotify.
+    if backend().as_spotify().is_some() {
+        follow_spotify();
+    }
     rt().spawn(async {
-        spotify()
-            .on_player_event(|e| {
+        backend()
+            .on_player_event(Box::new(|e| {
                 handle_player_event(e);
-            })
+            }))
             .await;
     });
     Ok(())
 }
 
+/// Mirrors output errors and the playback of a controlled remote device into the player.
+fn follow_spotify() {
+    rt().spawn(async {
+        spotify()
+            .on_output_error(|e| {
+                player::set_output_error(e).unwrap();
+            })
+            .await;
+    });
+    rt().spawn(async {
+        let current_item = std::sync::Mutex::new(None);
+        spotify()
+            .on_remote_playback(|playback| {
+                let Some(playback) = playback else {
+                    player::set_device_name("".into()).unwrap();
+                    player::pause().unwrap();
+                    return;
+                };
+                if let Some(rspotify::model::PlayableItem::Track(track)) = &playback.item
+                    && let Some(id) = &track.id
+                {
+                    use rspotify::prelude::Id;
+                    let id = id.id().to_string();
+                    let mut current = current_item.lock().unwrap();
+                    if current.as_ref() != Some(&id) {
+                        tracks::set_current_track(id.clone()).unwrap();
+                        if let Ok(track_id) = SpotifyId::from_uri(&format!("spotify:track:{}", id))
+                        {
+                            let artist = track.artists.first().map(|a| a.name.clone());
+                            lyrics_vm::fetch_lyrics(
+                                track_id,
+                                track.name.clone(),
+                                artist.unwrap_or_default(),
+                            );
+                        }
+                        if let Some(url) = models::large_image(&track.album.images) {
+                            let url = url.to_string();
+                            rt().spawn(async move {
+                                match backend().fetch_cover_art(url).await {
+                                    Ok(img) => player::set_cover_art(img).unwrap(),
+                                    Err(e) => log::error!("Failed to fetch cover art: {}", e),
+                                }
+                            });
+                        }
+                        *current = Some(id);
+                    }
+                }
+                player::set_remote_playback(playback).unwrap();
+            })
+            .await;
+    });
+}
+
 fn handle_player_event(event: librespot_playback::player::PlayerEvent) {
+    #[cfg(target_os = "linux")]
+    super::mpris_vm::player_event(&event);
     match event {
         librespot_playback::player::PlayerEvent::PlayRequestIdChanged { play_request_id } => {
             log::info!("Play request id changed to {}", play_request_id);
@@ -66,8 +145,7 @@ fn handle_player_event(event: librespot_playback::player::PlayerEvent) {
             player::set_position(position_ms).unwrap();
         }
         librespot_playback::player::PlayerEvent::TimeToPreloadNextTrack { track_id, .. } => {
-            log::info!("Preloading track: {}", track_id);
-            spotify().player.preload(track_id);
+            log::debug!("Time to preload after {}", track_id);
         }
         librespot_playback::player::PlayerEvent::EndOfTrack { track_id, .. } => {
             log::info!("Track finished for {}", track_id);
@@ -76,6 +154,7 @@ fn handle_player_event(event: librespot_playback::player::PlayerEvent) {
         }
         librespot_playback::player::PlayerEvent::Unavailable { track_id, .. } => {
             log::error!("Track unavailable: {}", track_id);
+            toast::show_error(&Error::Playback("This track is unavailable".into())).unwrap();
         }
         librespot_playback::player::PlayerEvent::PositionCorrection { position_ms, .. } => {
             player::set_position(position_ms).unwrap();
@@ -92,13 +171,38 @@ fn handle_player_event(event: librespot_playback::player::PlayerEvent) {
             if let Some(url) = audio_item.covers.first() {
                 let url = url.url.clone();
                 rt().spawn(async move {
-                    match spotify().fetch_cover_art(url).await {
+                    match backend().fetch_cover_art(url).await {
                         Ok(img) => player::set_cover_art(img).unwrap(),
                         Err(e) => log::error!("Failed to fetch cover art: {}", e),
                     }
                 });
             }
+            let episode_id = (audio_item.track_id.item_type
+                == librespot_core::spotify_id::SpotifyItemType::Episode)
+                .then(|| audio_item.track_id.to_base62().ok())
+                .flatten();
+            if let Ok(id) = audio_item.track_id.to_base62() {
+                tracks::set_current_track(id).unwrap();
+            }
+            let artist = match &audio_item.unique_fields {
+                librespot_metadata::audio::UniqueFields::Track { artists, .. } => artists
+                    .0
+                    .first()
+                    .map(|a| a.name.clone())
+                    .unwrap_or_default(),
+                librespot_metadata::audio::UniqueFields::Episode { show_name, .. } => {
+                    show_name.clone()
+                }
+            };
+            lyrics_vm::fetch_lyrics(audio_item.track_id, audio_item.name.clone(), artist);
+            let quality = backend()
+                .streaming_quality(&audio_item.files)
+                .unwrap_or_default();
+            player::set_streaming_quality(quality).unwrap();
             player::set_track_details(audio_item).unwrap();
+            if let Some(id) = episode_id {
+                fetch_publisher(id);
+            }
             player::pause().unwrap();
             player::set_position(0).unwrap();
         }
@@ -128,13 +232,39 @@ fn handle_player_event(event: librespot_playback::player::PlayerEvent) {
                 client_model_name
             );
         }
-        // librespot_playback::player::PlayerEvent::ShuffleChanged { shuffle } => todo!(),
-        // librespot_playback::player::PlayerEvent::RepeatChanged { context, track } => todo!(),
+        librespot_playback::player::PlayerEvent::ShuffleChanged { shuffle } => {
+            player::set_shuffle(shuffle).unwrap();
+        }
+        librespot_playback::player::PlayerEvent::RepeatChanged { context, track } => {
+            player::set_repeat(context, track).unwrap();
+        }
         // librespot_playback::player::PlayerEvent::AutoPlayChanged { auto_play } => todo!(),
         // librespot_playback::player::PlayerEvent::FilterExplicitContentChanged { filter } => todo!(),
-        // librespot_playback::player::PlayerEvent::VolumeChanged { volume } => todo!(),
+        librespot_playback::player::PlayerEvent::VolumeChanged { volume } => {
+            player::set_volume(volume).unwrap();
+        }
         _ => {
             log::info!("{:#?}", event);
         }
     }
 }
+
+/// The audio item of an episode carries no publisher, so it comes from the Web API.
+fn fetch_publisher(id: String) {
+    let Some(spotify) = backend().as_spotify() else {
+        return;
+    };
+    rt().spawn(async move {
+        let episode_id = match EpisodeId::from_id(id.as_str()) {
+            Ok(episode_id) => episode_id,
+            Err(e) => {
+                log::error!("Invalid episode id {}: {}", id, e);
+                return;
+            }
+        };
+        match spotify.episode(episode_id).await {
+            Ok(episode) => player::set_publisher(episode.show.publisher).unwrap(),
+            Err(e) => log::error!("Failed to fetch episode {}: {:#}", id, e),
+        }
+    });
+}
@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::StreamExt;
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::models::{self, playlists, toast, tracks};
+use crate::services::{backend, rt, ui_weak};
+
+use super::tracks_vm;
+
+/// Bumped on every refresh so pages of an older one are dropped.
+static FETCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::PlaylistsState>();
+    app.on_fetch_playlists(|| {
+        rt().spawn(fetch_playlists());
+    });
+    app.on_playlist_clicked(|id| {
+        log::info!("Playlist clicked: {}", id);
+        let ui = ui_weak().unwrap();
+        let name = ui
+            .global::<crate::PlaylistsState>()
+            .get_playlists()
+            .iter()
+            .find(|p| p.id == id)
+            .map(|p| p.name.to_string())
+            .unwrap_or_default();
+        tracks::set_title(name).unwrap();
+        tracks_vm::fetch_playlist_tracks(id.into());
+    });
+    Ok(())
+}
+
+async fn fetch_playlists() {
+    let generation = FETCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    playlists::set_fetching_playlists(true).unwrap();
+    playlists::clear_playlists().unwrap();
+    let mut pages = std::pin::pin!(backend().user_playlists());
+    while let Some(page) = pages.next().await {
+        if !is_current(generation) {
+            return;
+        }
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                log::error!("Failed to fetch playlists: {:#}", e);
+                toast::show_error(&e).unwrap();
+                break;
+            }
+        };
+        for playlist in &page {
+            if let Some(url) = models::thumbnail(&playlist.images) {
+                let id = playlist.id.id().to_string();
+                let url = url.to_string();
+                rt().spawn(async move {
+                    match backend().fetch_cover_art(url).await {
+                        Ok(img) => playlists::set_cover_art(id, img).unwrap(),
+                        Err(e) => log::error!("Failed to fetch playlist cover: {}", e),
+                    }
+                });
+            }
+        }
+        playlists::add_playlists(page).unwrap();
+    }
+    if is_current(generation) {
+        playlists::set_fetching_playlists(false).unwrap();
+    }
+}
+
+fn is_current(generation: u64) -> bool {
+    FETCH_GENERATION.load(Ordering::SeqCst) == generation
+}
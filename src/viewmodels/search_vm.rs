@@ -0,0 +1,194 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use rspotify::model::SearchType;
+use slint::{ComponentHandle, Model};
+
+use crate::models::{search, tracks};
+use crate::services::{backend, rt, ui_weak};
+
+use super::{album_vm, artist_vm, shows_vm, tracks_vm};
+
+/// How long the query has to stay unchanged before it is sent.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// Results per type when searching all types at once.
+const PREVIEW_LIMIT: u32 = 5;
+/// Results per request when searching a single type or loading more.
+const PAGE_LIMIT: u32 = 20;
+
+const ALL_TYPES: [SearchType; 6] = [
+    SearchType::Track,
+    SearchType::Artist,
+    SearchType::Album,
+    SearchType::Playlist,
+    SearchType::Show,
+    SearchType::Episode,
+];
+
+/// Bumped on every keystroke so stale searches and their pages are dropped.
+static SEARCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::SearchState>();
+    app.on_search(|query, filter| {
+        let generation = SEARCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+        let query = query.trim().to_string();
+        let types = search_types(&filter);
+        rt().spawn(async move {
+            tokio::time::sleep(DEBOUNCE).await;
+            if !is_current(generation) {
+                return;
+            }
+            search::set_error("".into()).unwrap();
+            if query.is_empty() {
+                search::set_searching(false).unwrap();
+                search::set_sections(vec![]).unwrap();
+                return;
+            }
+            let limit = if types.len() > 1 {
+                PREVIEW_LIMIT
+            } else {
+                PAGE_LIMIT
+            };
+            let Some(spotify) = backend().as_spotify() else {
+                search::set_searching(false).unwrap();
+                search::set_error("Search is only available on Spotify".into()).unwrap();
+                return;
+            };
+            search::set_searching(true).unwrap();
+            let result = spotify.search(&query, &types, limit, 0).await;
+            if !is_current(generation) {
+                return;
+            }
+            search::set_searching(false).unwrap();
+            match result {
+                Ok(result) => {
+                    let sections = search::sections(result);
+                    fetch_cover_art(&sections);
+                    search::set_sections(sections).unwrap();
+                }
+                Err(e) => {
+                    log::error!("Search for {:?} failed: {:#}", query, e);
+                    search::set_error(format!("Search failed: {}", e)).unwrap();
+                }
+            }
+        });
+    });
+    app.on_load_more(|kind| {
+        let ui = ui_weak().unwrap();
+        let state = ui.global::<crate::SearchState>();
+        let Some(section) = state.get_sections().iter().find(|s| s.kind == kind) else {
+            return;
+        };
+        let Some(kind) = search_type(&kind) else {
+            return;
+        };
+        let query = state.get_query().trim().to_string();
+        let offset = section.next_offset as u32;
+        let generation = SEARCH_GENERATION.load(Ordering::SeqCst);
+        let Some(spotify) = backend().as_spotify() else {
+            return;
+        };
+        rt().spawn(async move {
+            let result = spotify.search(&query, &[kind], PAGE_LIMIT, offset).await;
+            if !is_current(generation) {
+                return;
+            }
+            match result {
+                Ok(result) => {
+                    for section in search::sections(result) {
+                        fetch_cover_art(std::slice::from_ref(&section));
+                        search::extend_section(section).unwrap();
+                    }
+                }
+                Err(e) => log::error!("Failed to load more results for {:?}: {:#}", query, e),
+            }
+        });
+    });
+    app.on_item_clicked(|kind, index| {
+        let ui = ui_weak().unwrap();
+        let state = ui.global::<crate::SearchState>();
+        let Some(item) = state
+            .get_sections()
+            .iter()
+            .find(|s| s.kind == kind)
+            .and_then(|s| s.items.row_data(index as usize))
+        else {
+            return;
+        };
+        log::info!("Search result clicked: {} {}", kind, item.id);
+        let id = item.id.to_string();
+        let title = item.title.to_string();
+        match search_type(&kind) {
+            Some(SearchType::Track) => {
+                backend()
+                    .load_track(id)
+                    .unwrap_or_else(|e| log::error!("Failed to load track: {}", e));
+                return;
+            }
+            // Album, artist and show pages open on top of the results.
+            Some(SearchType::Album) => {
+                album_vm::open_album(id);
+                return;
+            }
+            Some(SearchType::Artist) => {
+                artist_vm::open_artist(id);
+                return;
+            }
+            Some(SearchType::Show) => {
+                shows_vm::open_show(id);
+                return;
+            }
+            Some(SearchType::Episode) => {
+                shows_vm::play_episode(id);
+                return;
+            }
+            Some(SearchType::Playlist) => {
+                tracks::set_title(title).unwrap();
+                tracks_vm::fetch_playlist_tracks(id);
+            }
+            _ => {
+                log::info!("Playing {} results is not supported yet", kind);
+                return;
+            }
+        }
+        state.set_open(false);
+    });
+    Ok(())
+}
+
+fn search_types(filter: &str) -> Vec<SearchType> {
+    match search_type(filter) {
+        Some(kind) => vec![kind],
+        None => ALL_TYPES.to_vec(),
+    }
+}
+
+fn search_type(kind: &str) -> Option<SearchType> {
+    ALL_TYPES
+        .into_iter()
+        .find(|t| Into::<&str>::into(*t) == kind)
+}
+
+fn fetch_cover_art(sections: &[search::Section]) {
+    for section in sections {
+        for item in &section.items {
+            let Some(url) = item.image.clone() else {
+                continue;
+            };
+            let kind = section.kind;
+            let id = item.id.clone();
+            rt().spawn(async move {
+                match backend().fetch_cover_art(url).await {
+                    Ok(img) => search::set_cover_art(kind, id, img).unwrap(),
+                    Err(e) => log::error!("Failed to fetch search cover: {}", e),
+                }
+            });
+        }
+    }
+}
+
+fn is_current(generation: u64) -> bool {
+    SEARCH_GENERATION.load(Ordering::SeqCst) == generation
+}
@@ -0,0 +1,139 @@
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+
+use crate::services::{output, settings, settings::DataSaver, ui_weak};
+
+const DEFAULT_DEVICE: &str = "Default";
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::SettingsState>();
+    show_settings(&app, &settings().read().unwrap());
+    if let Some(error) = &settings().read().unwrap().load_error {
+        app.set_status(format!("Using defaults, the settings file is invalid: {}", error).into());
+    }
+    app.on_save_clicked(|| {
+        let ui = ui_weak().unwrap();
+        let app = ui.global::<crate::SettingsState>();
+        let status = match save_settings(&app) {
+            Ok(()) => "Saved. Audio changes apply the next time Taan starts.".to_string(),
+            Err(e) => {
+                log::error!("Failed to save settings: {:#}", e);
+                format!("{:#}", e)
+            }
+        };
+        app.set_status(status.into());
+    });
+    app.on_refresh_devices(|backend| {
+        let ui = ui_weak().unwrap();
+        let app = ui.global::<crate::SettingsState>();
+        app.set_devices(device_model(Some(backend.as_str())));
+    });
+    app.on_output_selected(|backend, device| {
+        let ui = ui_weak().unwrap();
+        let app = ui.global::<crate::SettingsState>();
+        let status = match select_output(backend.as_str(), device.as_str()) {
+            Ok(()) => {
+                ui.global::<crate::PlayerState>()
+                    .set_output_error("".into());
+                format!("Playing through {}", device)
+            }
+            Err(e) => {
+                log::error!("Failed to switch output: {:#}", e);
+                format!("{:#}", e)
+            }
+        };
+        app.set_status(status.into());
+    });
+    Ok(())
+}
+
+fn string_model(items: Vec<String>) -> ModelRc<SharedString> {
+    ModelRc::new(VecModel::from(
+        items
+            .into_iter()
+            .map(SharedString::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+fn device_model(backend: Option<&str>) -> ModelRc<SharedString> {
+    let mut devices = vec![DEFAULT_DEVICE.to_string()];
+    devices.extend(output::devices(backend));
+    string_model(devices)
+}
+
+fn select_output(backend: &str, device: &str) -> anyhow::Result<()> {
+    let backend = Some(backend.to_string()).filter(|b| !b.is_empty());
+    let device = Some(device.to_string()).filter(|d| !d.is_empty() && d != DEFAULT_DEVICE);
+    let spotify = crate::services::backend()
+        .as_spotify()
+        .ok_or_else(|| anyhow::anyhow!("Audio outputs are only available on Spotify"))?;
+    spotify.switch_output(backend.clone(), device.clone())?;
+    let mut settings = settings().write().unwrap();
+    settings.audio.backend = backend;
+    settings.audio.device = device;
+    settings.save()
+}
+
+fn show_settings(app: &crate::SettingsState, settings: &settings::Settings) {
+    app.set_show_album_column(settings.ui.show_album_column);
+    let backends = output::backends();
+    let backend = settings
+        .audio
+        .backend
+        .clone()
+        .or_else(|| backends.first().cloned())
+        .unwrap_or_default();
+    app.set_devices(device_model(Some(&backend)));
+    app.set_backend(backend.into());
+    app.set_backends(string_model(backends));
+    app.set_device(
+        settings
+            .audio
+            .device
+            .as_deref()
+            .unwrap_or(DEFAULT_DEVICE)
+            .into(),
+    );
+    app.set_bitrate(settings.audio.bitrate.to_string().into());
+    app.set_metered_bitrate(settings.audio.metered_bitrate.to_string().into());
+    app.set_data_saver(
+        match settings.audio.data_saver {
+            DataSaver::Auto => "auto",
+            DataSaver::Always => "always",
+            DataSaver::Never => "never",
+        }
+        .into(),
+    );
+    let n = &settings.normalisation;
+    app.set_normalisation_enabled(n.enabled);
+    app.set_normalisation_type(n.gain_type.as_str().into());
+    app.set_normalisation_method(n.method.as_str().into());
+    app.set_pregain_db(n.pregain_db as f32);
+    app.set_threshold_dbfs(n.threshold_dbfs as f32);
+    app.set_attack_ms(n.attack_ms as i32);
+    app.set_release_ms(n.release_ms as i32);
+}
+
+fn save_settings(app: &crate::SettingsState) -> anyhow::Result<()> {
+    let mut updated = settings().read().unwrap().clone();
+    updated.audio.bitrate = app.get_bitrate().parse()?;
+    updated.audio.metered_bitrate = app.get_metered_bitrate().parse()?;
+    updated.audio.data_saver = match app.get_data_saver().as_str() {
+        "always" => DataSaver::Always,
+        "never" => DataSaver::Never,
+        _ => DataSaver::Auto,
+    };
+    let n = &mut updated.normalisation;
+    n.enabled = app.get_normalisation_enabled();
+    n.gain_type = app.get_normalisation_type().into();
+    n.method = app.get_normalisation_method().into();
+    n.pregain_db = (app.get_pregain_db() as f64 * 10.0).round() / 10.0;
+    n.threshold_dbfs = (app.get_threshold_dbfs() as f64 * 10.0).round() / 10.0;
+    n.attack_ms = app.get_attack_ms() as u64;
+    n.release_ms = app.get_release_ms() as u64;
+    updated.validate()?;
+    updated.save()?;
+    *settings().write().unwrap() = updated;
+    Ok(())
+}
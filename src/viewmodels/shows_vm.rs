@@ -0,0 +1,161 @@
+use futures::StreamExt;
+use rspotify::model::{EpisodeId, ShowId};
+use rspotify::prelude::*;
+use slint::{ComponentHandle, Model};
+
+use crate::models::{self, shows, toast};
+use crate::services::{backend, rt, ui_weak};
+
+pub fn register_handlers() -> anyhow::Result<()> {
+    let ui = ui_weak().unwrap();
+    let app = ui.global::<crate::ShowsState>();
+    app.on_fetch_shows(|| {
+        rt().spawn(fetch_shows());
+    });
+    let app = ui.global::<crate::ShowState>();
+    app.on_open_show(|id| {
+        open_show(id.into());
+    });
+    app.on_episode_clicked(|index| {
+        let ui = ui_weak().unwrap();
+        let state = ui.global::<crate::ShowState>();
+        let episodes = state.get_episodes();
+        let Some(episode) = episodes.row_data(index as usize) else {
+            return;
+        };
+        let position_ms = if episode.fully_played {
+            0
+        } else {
+            episode.resume_position as u32
+        };
+        let ids: Vec<String> = episodes.iter().map(|e| e.id.into()).collect();
+        let context_uri = format!("spotify:show:{}", state.get_id());
+        let Some(spotify) = backend().as_spotify() else {
+            log::warn!("Podcasts are only available on Spotify");
+            return;
+        };
+        spotify
+            .play_episodes(Some(context_uri), &ids, index as usize, position_ms)
+            .unwrap_or_else(|e| log::error!("Failed to play episode: {:#}", e));
+    });
+    Ok(())
+}
+
+async fn fetch_shows() {
+    // Other backends have no podcasts, the list stays empty.
+    let Some(spotify) = backend().as_spotify() else {
+        return;
+    };
+    shows::set_fetching_shows(true).unwrap();
+    shows::clear_shows().unwrap();
+    let mut pages = std::pin::pin!(spotify.saved_shows());
+    while let Some(page) = pages.next().await {
+        let page = match page {
+            Ok(page) => page,
+            Err(e) => {
+                log::error!("Failed to fetch saved shows: {:#}", e);
+                toast::show_error(&e).unwrap();
+                break;
+            }
+        };
+        for saved in &page {
+            if let Some(url) = models::thumbnail(&saved.show.images) {
+                let id = saved.show.id.id().to_string();
+                let url = url.to_string();
+                rt().spawn(async move {
+                    match backend().fetch_cover_art(url).await {
+                        Ok(img) => shows::set_show_cover_art(id, img).unwrap(),
+                        Err(e) => log::error!("Failed to fetch show cover: {}", e),
+                    }
+                });
+            }
+        }
+        shows::add_shows(page).unwrap();
+    }
+    shows::set_fetching_shows(false).unwrap();
+}
+
+/// Opens the page of show `id`, loading its details and episodes.
+pub fn open_show(id: String) {
+    log::info!("Opening show {}", id);
+    shows::open(id.clone()).unwrap();
+    let Some(spotify) = backend().as_spotify() else {
+        shows::set_error("Podcasts are only available on Spotify".into()).unwrap();
+        return;
+    };
+    shows::set_fetching(true).unwrap();
+    rt().spawn(async move {
+        let show_id = match ShowId::from_id(id.clone()) {
+            Ok(show_id) => show_id,
+            Err(e) => {
+                log::error!("Invalid show id {}: {}", id, e);
+                shows::set_fetching(false).unwrap();
+                return;
+            }
+        };
+        match spotify.show(show_id.clone()).await {
+            Ok(details) => {
+                if let Some(url) = models::large_image(&details.images) {
+                    let id = id.clone();
+                    let url = url.to_string();
+                    rt().spawn(async move {
+                        match backend().fetch_cover_art(url).await {
+                            Ok(img) => shows::set_cover_art(id, img).unwrap(),
+                            Err(e) => log::error!("Failed to fetch show cover: {}", e),
+                        }
+                    });
+                }
+                shows::set_details(details).unwrap();
+            }
+            Err(e) => {
+                log::error!("Failed to fetch show {}: {:#}", id, e);
+                shows::set_error(format!("Failed to fetch show: {:#}", e)).unwrap();
+                shows::set_fetching(false).unwrap();
+                return;
+            }
+        }
+        let mut pages = std::pin::pin!(spotify.show_episodes(show_id));
+        while let Some(page) = pages.next().await {
+            match page {
+                Ok(episodes) => shows::add_episodes(id.clone(), episodes).unwrap(),
+                Err(e) => {
+                    log::error!("Failed to fetch episodes: {:#}", e);
+                    toast::show_error(&e).unwrap();
+                    break;
+                }
+            }
+        }
+        shows::set_fetching(false).unwrap();
+    });
+}
+
+/// Plays a single episode from where the user stopped listening.
+pub fn play_episode(id: String) {
+    let Some(spotify) = backend().as_spotify() else {
+        log::warn!("Podcasts are only available on Spotify");
+        return;
+    };
+    rt().spawn(async move {
+        let episode_id = match EpisodeId::from_id(id.as_str()) {
+            Ok(episode_id) => episode_id,
+            Err(e) => {
+                log::error!("Invalid episode id {}: {}", id, e);
+                return;
+            }
+        };
+        let position_ms = match spotify.episode(episode_id).await {
+            Ok(episode) => episode
+                .resume_point
+                .filter(|p| !p.fully_played)
+                .map(|p| p.resume_position.num_milliseconds() as u32)
+                .unwrap_or_default(),
+            Err(e) => {
+                log::error!("Failed to fetch resume point of {}: {:#}", id, e);
+                0
+            }
+        };
+        spotify
+            .play_episodes(None, &[id], 0, position_ms)
+            .unwrap_or_else(|e| log::error!("Failed to play episode: {:#}", e));
+    });
+}
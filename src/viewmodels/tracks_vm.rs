@@ -1,25 +1,113 @@
-use crate::services::{rt, spotify, ui_weak};
-use slint::ComponentHandle;
-use crate::models::tracks::add_tracks;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::models::toast;
+use crate::models::tracks::{
+    add_tracks, clear_tracks, set_context_uri, set_fetching_tracks, set_title,
+};
+use crate::services::{backend, error, rt, ui_weak};
+use futures::{Stream, StreamExt};
+use rspotify::model::{FullTrack, PlayableItem, PlaylistId};
+use slint::{ComponentHandle, Model};
+
+/// Bumped whenever another list is requested so pages of the previous one are dropped.
+static FETCH_GENERATION: AtomicU64 = AtomicU64::new(0);
 
 pub fn register_handlers() -> anyhow::Result<()> {
     ui_weak().upgrade_in_event_loop(|ui| {
         let tracks = ui.global::<crate::TracksState>();
-        tracks.on_track_clicked(|track| {
-            log::info!("Track clicked: {}", track);
-            spotify().load_track(track.into()).unwrap_or_else(|e| log::error!("Failed to load track: {}", e));
+        tracks.on_track_clicked(|track, index| {
+            log::info!("Track clicked: {} at {}", track, index);
+            let ui = ui_weak().unwrap();
+            let state = ui.global::<crate::TracksState>();
+            let context_uri = state.get_context_uri();
+            if context_uri.is_empty() {
+                backend()
+                    .load_track(track.into())
+                    .unwrap_or_else(|e| log::error!("Failed to load track: {}", e));
+                return;
+            }
+            let ids: Vec<String> = state.get_tracks().iter().map(|t| t.id.into()).collect();
+            backend()
+                .play_context(context_uri.into(), &ids, index as usize)
+                .unwrap_or_else(|e| log::error!("Failed to play track: {:#}", e));
         });
         tracks.on_fetch_tracks(|plist| {
             log::info!("Fetch tracks: {}", plist);
+            fetch_playlist_tracks(plist.into());
         });
         tracks.on_fetch_saved_tracks(|| {
-            rt().spawn(async {
-                let user_tracks = spotify().get_saved_tracks().await.expect("Spotify client not initialized, fatal");
-                let tracks: Vec<_> = user_tracks.into_iter().map(|item| item.track).collect();
-                add_tracks(tracks).unwrap_or_else(|e| log::error!("Failed to add tracks: {}", e))
+            let generation = start_fetch();
+            set_title("Liked Songs".into()).unwrap();
+            set_context_uri(backend().liked_songs_uri()).unwrap();
+            rt().spawn(async move {
+                let pages = backend().saved_tracks().map(|page| {
+                    page.map(|items| items.into_iter().map(|item| item.track).collect())
+                });
+                fill_tracks(pages, generation).await;
             });
         });
     })?;
 
     Ok(())
 }
+
+/// Replaces the track list with the items of playlist `id`.
+pub fn fetch_playlist_tracks(id: String) {
+    let generation = start_fetch();
+    set_context_uri(format!("spotify:playlist:{}", id)).unwrap();
+    rt().spawn(async move {
+        let playlist_id = match PlaylistId::from_id(id.clone()) {
+            Ok(playlist_id) => playlist_id,
+            Err(e) => {
+                log::error!("Invalid playlist id {}: {}", id, e);
+                set_fetching_tracks(false).unwrap();
+                return;
+            }
+        };
+        let pages = backend().playlist_items(playlist_id).map(|page| {
+            page.map(|items| {
+                items
+                    .into_iter()
+                    .filter_map(|item| match item.track {
+                        Some(PlayableItem::Track(track)) => Some(track),
+                        _ => None,
+                    })
+                    .collect()
+            })
+        });
+        fill_tracks(pages, generation).await;
+    });
+}
+
+/// Appends pages to the track list as they arrive, until the list is replaced by another one.
+async fn fill_tracks(pages: impl Stream<Item = error::Result<Vec<FullTrack>>>, generation: u64) {
+    let mut pages = std::pin::pin!(pages);
+    while let Some(page) = pages.next().await {
+        if !is_current(generation) {
+            return;
+        }
+        match page {
+            Ok(tracks) => {
+                add_tracks(tracks).unwrap_or_else(|e| log::error!("Failed to add tracks: {}", e))
+            }
+            Err(e) => {
+                log::error!("Failed to fetch tracks: {:#}", e);
+                toast::show_error(&e).unwrap();
+                break;
+            }
+        }
+    }
+    if is_current(generation) {
+        set_fetching_tracks(false).unwrap();
+    }
+}
+
+fn start_fetch() -> u64 {
+    clear_tracks().unwrap();
+    set_fetching_tracks(true).unwrap();
+    FETCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+fn is_current(generation: u64) -> bool {
+    FETCH_GENERATION.load(Ordering::SeqCst) == generation
+}